@@ -1,18 +1,210 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+
+use crate::deps::GraphFormat;
+use crate::launchd_config::KeepAlive;
+use crate::services::service_settings::Overrides;
+use crate::status_report::OutputFormat;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 pub struct Cli {
+    /// Only look for services in this source, e.g. homebrew or useragent
+    #[arg(long, global = true)]
+    pub source: Option<String>,
+
+    /// Treat service names as exact launchd labels, e.g. com.example.redis
+    #[arg(long, global = true)]
+    pub label: bool,
+
+    /// Re-run through sudo when a service requires root
+    #[arg(long, global = true)]
+    pub sudo: bool,
+
     #[command(subcommand)]
     pub command: Commands,
 }
 
 #[derive(Subcommand, Debug)]
 pub enum Commands {
-    /// Start a service by formula
-    Start { formula: String },
+    /// Start a service by formula, after the services it requires
+    Start {
+        formula: String,
+        /// Take over processes of the service started outside flint instead of starting another
+        #[arg(long)]
+        adopt: bool,
+        /// Wait until the service passes its health check
+        #[arg(long)]
+        wait: bool,
+        #[command(flatten)]
+        overrides: OverrideArgs,
+    },
     /// Stop a service by formula
-    Stop { formula: String },
+    Stop {
+        formula: String,
+        #[command(flatten)]
+        overrides: OverrideArgs,
+    },
+    /// Stop then start a service
+    Restart {
+        formula: String,
+        #[command(flatten)]
+        overrides: OverrideArgs,
+    },
+    /// Ask a service to reload its configuration (SIGHUP unless configured otherwise)
+    Reload { formula: String },
+    /// Send a signal to a service's process group
+    Kill {
+        formula: String,
+        /// Signal name or number, e.g. TERM, SIGUSR1 or 9
+        #[arg(long, short, default_value = "TERM")]
+        signal: String,
+    },
+    /// Adopt an already running process by matching its binary name
+    Adopt { formula: String },
     /// Query status
-    Status { formula: Option<String> },
+    Status {
+        formula: Option<String>,
+        /// Take over processes of the service started outside flint
+        #[arg(long, requires = "formula")]
+        adopt: bool,
+        /// Output format (default: output_format of the global config, else table)
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+    },
+    /// Show every detail of a service, including its recent history
+    Info {
+        formula: String,
+        /// Output format (default: output_format of the global config, else table)
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+    },
+    /// List all services and their status
+    List {
+        /// Output format (default: output_format of the global config, else table)
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+    },
+    /// Show the logs of one or more services
+    Logs {
+        #[arg(required = true)]
+        formulas: Vec<String>,
+        /// Number of lines to show from the end of each log
+        #[arg(short = 'n', long, default_value_t = 10)]
+        lines: usize,
+        /// Keep printing new lines as they are written
+        #[arg(short, long)]
+        follow: bool,
+        /// Only show stdout
+        #[arg(long, conflicts_with_all = ["stderr", "both"])]
+        stdout: bool,
+        /// Only show stderr
+        #[arg(long, conflicts_with = "both")]
+        stderr: bool,
+        /// Show stdout and stderr (the default)
+        #[arg(long)]
+        both: bool,
+        /// Only show logs written since then, e.g. 15m, 2h, 1d or 2024-05-01T12:00:00Z
+        #[arg(long)]
+        since: Option<String>,
+    },
+    /// Run services in the foreground, restarting them according to KeepAlive
+    Supervise {
+        #[arg(required = true)]
+        formulas: Vec<String>,
+        #[command(flatten)]
+        overrides: OverrideArgs,
+    },
+    /// Show what a service requires or starts after
+    Deps {
+        formula: String,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = GraphFormat::Tree)]
+        format: GraphFormat,
+    },
+    /// Start the services of the flint.toml or Procfile of this directory or a parent,
+    /// and print their output until Ctrl-C stops them
+    Up {
+        /// Services of the project to start (default: all)
+        services: Vec<String>,
+    },
+    /// Stop the services of the project of this directory or a parent
+    Down {
+        /// Services of the project to stop (default: all)
+        services: Vec<String>,
+    },
+    /// Inspect the configuration of services
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Show the effective configuration of a service and where each value comes from
+    Show {
+        formula: String,
+        #[command(flatten)]
+        overrides: OverrideArgs,
+        /// Output format (default: output_format of the global config, else table)
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+    },
+}
+
+/// Settings given on the command line, taking precedence over the plist and
+/// the per-service config.
+#[derive(Args, Debug, Default, Clone)]
+pub struct OverrideArgs {
+    /// Pass this argument to the program instead of the configured ones; repeatable
+    #[arg(long = "arg", value_name = "ARG", allow_hyphen_values = true)]
+    pub args: Vec<String>,
+    /// Set an environment variable of the service; repeatable
+    #[arg(long = "env", value_name = "KEY=VALUE", value_parser = parse_env)]
+    pub env: Vec<(String, String)>,
+    /// Run the service in this directory
+    #[arg(long)]
+    pub working_directory: Option<String>,
+    /// Write the service's stdout to this file
+    #[arg(long)]
+    pub stdout_path: Option<String>,
+    /// Write the service's stderr to this file
+    #[arg(long)]
+    pub stderr_path: Option<String>,
+    /// Restart the service whenever it exits, or never, under supervise
+    #[arg(long, value_name = "BOOL")]
+    pub keep_alive: Option<bool>,
+    /// Minimum seconds between two launches when restarting
+    #[arg(long, value_name = "SECS")]
+    pub throttle_interval: Option<u64>,
+    /// Signal sent first when stopping, e.g. INT or QUIT (default: TERM)
+    #[arg(long, value_name = "SIGNAL")]
+    pub stop_signal: Option<String>,
+    /// Seconds to wait after the stop signal before sending SIGKILL (default: plist ExitTimeOut)
+    #[arg(long, visible_alias = "timeout", value_name = "SECS")]
+    pub stop_timeout: Option<u64>,
+}
+
+impl OverrideArgs {
+    pub fn overrides(&self) -> Overrides {
+        Overrides {
+            args: (!self.args.is_empty()).then(|| self.args.clone()),
+            environment_variables: self.env.iter().cloned().collect(),
+            working_directory: self.working_directory.clone(),
+            standard_out_path: self.stdout_path.clone(),
+            standard_error_path: self.stderr_path.clone(),
+            keep_alive: self.keep_alive.map(KeepAlive::Always),
+            throttle_interval: self.throttle_interval,
+            stop_signal: self.stop_signal.clone(),
+            exit_timeout: self.stop_timeout,
+            ..Overrides::default()
+        }
+    }
+}
+
+fn parse_env(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got '{}'", value)),
+    }
 }
@@ -0,0 +1,334 @@
+use std::collections::BTreeSet;
+
+use clap::ValueEnum;
+
+use crate::error::{Error, Result};
+use crate::service_manager::ServiceManager;
+use crate::services::service_state::ServiceStatus;
+
+/// How a service depends on another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DependencyKind {
+    /// The other service must be running; starting this one starts it first.
+    Requires,
+    /// Ordering only: the other service starts first when both are started.
+    After,
+}
+
+impl DependencyKind {
+    fn name(&self) -> &'static str {
+        match self {
+            DependencyKind::Requires => "requires",
+            DependencyKind::After => "after",
+        }
+    }
+}
+
+/// Output format of `flint deps`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum GraphFormat {
+    #[default]
+    Tree,
+    Dot,
+}
+
+/// An edge from a service to one it depends on, as node indices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dependency {
+    pub dependent: usize,
+    pub dependency: usize,
+    pub kind: DependencyKind,
+}
+
+/// Services and their `requires`/`after` relationships, keyed by launchd label.
+pub struct DependencyGraph {
+    managers: Vec<ServiceManager>,
+    /// Whether each service was asked for, rather than pulled in by `requires`.
+    roots: Vec<bool>,
+    dependencies: Vec<Dependency>,
+}
+
+impl DependencyGraph {
+    /// The services of `roots` and, transitively, every service they require.
+    ///
+    /// `after` only orders services already in the graph, so names it lists that
+    /// cannot be resolved are ignored; an unknown name in `requires` is an error.
+    pub fn resolve(roots: Vec<ServiceManager>) -> Result<Self> {
+        let mut graph = DependencyGraph {
+            managers: Vec::new(),
+            roots: Vec::new(),
+            dependencies: Vec::new(),
+        };
+        for manager in roots {
+            graph.add(manager, true);
+        }
+
+        let mut next = 0;
+        while next < graph.managers.len() {
+            for name in graph.managers[next].settings().requires.value.clone() {
+                let dependency = graph.managers[next].resolve_dependency(&name)?;
+                let dependency = graph.add(dependency, false);
+                graph.link(next, dependency, DependencyKind::Requires);
+            }
+            next += 1;
+        }
+
+        for dependent in 0..graph.managers.len() {
+            for name in graph.managers[dependent].settings().after.value.clone() {
+                let Ok(after) = graph.managers[dependent].resolve_dependency(&name) else {
+                    continue;
+                };
+                if let Some(dependency) = graph.position(after.service().label()) {
+                    graph.link(dependent, dependency, DependencyKind::After);
+                }
+            }
+        }
+        Ok(graph)
+    }
+
+    fn add(&mut self, manager: ServiceManager, root: bool) -> usize {
+        if let Some(index) = self.position(manager.service().label()) {
+            self.roots[index] |= root;
+            return index;
+        }
+        self.managers.push(manager);
+        self.roots.push(root);
+        self.managers.len() - 1
+    }
+
+    fn link(&mut self, dependent: usize, dependency: usize, kind: DependencyKind) {
+        let edge = Dependency {
+            dependent,
+            dependency,
+            kind,
+        };
+        if !self.dependencies.contains(&edge) {
+            self.dependencies.push(edge);
+        }
+    }
+
+    fn position(&self, label: &str) -> Option<usize> {
+        self.managers
+            .iter()
+            .position(|manager| manager.service().label() == label)
+    }
+
+    pub fn managers(&self) -> &[ServiceManager] {
+        &self.managers
+    }
+
+    pub fn dependencies(&self) -> &[Dependency] {
+        &self.dependencies
+    }
+
+    /// Every service, each after all the services it depends on.
+    ///
+    /// Services with no relationship keep the order they were added in.
+    pub fn start_order(&self) -> Result<Vec<&ServiceManager>> {
+        let mut placed = vec![false; self.managers.len()];
+        let mut order = Vec::new();
+        while order.len() < self.managers.len() {
+            let ready = (0..self.managers.len()).find(|&index| {
+                !placed[index]
+                    && self
+                        .dependencies_of(index)
+                        .all(|dependency| placed[dependency.dependency])
+            });
+            let Some(index) = ready else {
+                return Err(Error::DependencyCycle {
+                    cycle: self.find_cycle(&placed),
+                });
+            };
+            placed[index] = true;
+            order.push(&self.managers[index]);
+        }
+        Ok(order)
+    }
+
+    /// The services asked for, each before the services it depends on.
+    ///
+    /// Services pulled in by `requires` are left out, as other services may still need them.
+    pub fn stop_order(&self) -> Result<Vec<&ServiceManager>> {
+        let mut order = self.start_order()?;
+        order.retain(|manager| {
+            self.position(manager.service().label())
+                .is_some_and(|index| self.roots[index])
+        });
+        order.reverse();
+        Ok(order)
+    }
+
+    /// Fails unless every service `manager` requires is running.
+    pub fn check_requirements(&self, manager: &ServiceManager) -> Result<()> {
+        let Some(index) = self.position(manager.service().label()) else {
+            return Ok(());
+        };
+        for dependency in self.dependencies_of(index) {
+            if dependency.kind != DependencyKind::Requires {
+                continue;
+            }
+            let required = &self.managers[dependency.dependency];
+            let state = required.service_state().reconcile(false)?;
+            if state.status() != &ServiceStatus::Running {
+                return Err(Error::DependencyNotRunning {
+                    formula: manager.formula().to_string(),
+                    dependency: required.formula().to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts every service in dependency order, each once the services it
+    /// requires are running and have passed their health checks.
+    ///
+    /// With `wait`, the services asked for are waited for as well.
+    pub fn start(&self, wait: bool) -> Result<()> {
+        for manager in self.start_order()? {
+            self.check_requirements(manager)?;
+            if wait || self.is_required(manager) {
+                manager.start_and_wait()?;
+            } else {
+                manager.start()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether another service of the graph requires `manager`.
+    pub fn is_required(&self, manager: &ServiceManager) -> bool {
+        self.position(manager.service().label())
+            .is_some_and(|index| {
+                self.dependencies.iter().any(|dependency| {
+                    dependency.dependency == index && dependency.kind == DependencyKind::Requires
+                })
+            })
+    }
+
+    pub fn render(&self, format: GraphFormat) -> String {
+        match format {
+            GraphFormat::Tree => self.render_tree(),
+            GraphFormat::Dot => self.render_dot(),
+        }
+    }
+
+    /// Each service asked for, with what it depends on indented below it.
+    fn render_tree(&self) -> String {
+        let mut lines = Vec::new();
+        for index in (0..self.managers.len()).filter(|&index| self.roots[index]) {
+            lines.push(self.managers[index].formula().to_string());
+            self.render_children(index, "", &mut vec![index], &mut lines);
+        }
+        lines.join("\n")
+    }
+
+    fn render_children(
+        &self,
+        index: usize,
+        indent: &str,
+        path: &mut Vec<usize>,
+        lines: &mut Vec<String>,
+    ) {
+        let children: Vec<_> = self.dependencies_of(index).collect();
+        for (position, dependency) in children.iter().enumerate() {
+            let last = position + 1 == children.len();
+            let (branch, next_indent) = if last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            let child = dependency.dependency;
+            let cycle = path.contains(&child);
+            lines.push(format!(
+                "{}{}{} {}{}",
+                indent,
+                branch,
+                dependency.kind.name(),
+                self.managers[child].formula(),
+                if cycle { " (cycle)" } else { "" }
+            ));
+            if !cycle {
+                path.push(child);
+                self.render_children(child, &format!("{}{}", indent, next_indent), path, lines);
+                path.pop();
+            }
+        }
+    }
+
+    /// The graph in Graphviz DOT, `requires` as solid edges and `after` as dashed ones.
+    fn render_dot(&self) -> String {
+        let mut lines = vec!["digraph flint {".to_string()];
+        for manager in &self.managers {
+            lines.push(format!("    {};", quote(manager.formula())));
+        }
+        for dependency in &self.dependencies {
+            let style = match dependency.kind {
+                DependencyKind::Requires => "",
+                DependencyKind::After => ", style=dashed",
+            };
+            lines.push(format!(
+                "    {} -> {} [label={}{}];",
+                quote(self.managers[dependency.dependent].formula()),
+                quote(self.managers[dependency.dependency].formula()),
+                quote(dependency.kind.name()),
+                style
+            ));
+        }
+        lines.push("}".to_string());
+        lines.join("\n")
+    }
+
+    fn dependencies_of(&self, index: usize) -> impl Iterator<Item = &Dependency> {
+        self.dependencies
+            .iter()
+            .filter(move |dependency| dependency.dependent == index)
+    }
+
+    /// Formulas along a cycle among the services not yet `placed`, first one repeated last.
+    fn find_cycle(&self, placed: &[bool]) -> Vec<String> {
+        let mut finished = BTreeSet::new();
+        for start in (0..self.managers.len()).filter(|&index| !placed[index]) {
+            let mut path = Vec::new();
+            if let Some(cycle) = self.walk(start, placed, &mut path, &mut finished) {
+                return cycle
+                    .into_iter()
+                    .map(|index| self.managers[index].formula().to_string())
+                    .collect();
+            }
+        }
+        Vec::new()
+    }
+
+    fn walk(
+        &self,
+        index: usize,
+        placed: &[bool],
+        path: &mut Vec<usize>,
+        finished: &mut BTreeSet<usize>,
+    ) -> Option<Vec<usize>> {
+        if let Some(start) = path.iter().position(|&seen| seen == index) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(index);
+            return Some(cycle);
+        }
+        if finished.contains(&index) {
+            return None;
+        }
+        path.push(index);
+        for dependency in self.dependencies_of(index) {
+            if placed[dependency.dependency] {
+                continue;
+            }
+            if let Some(cycle) = self.walk(dependency.dependency, placed, path, finished) {
+                return Some(cycle);
+            }
+        }
+        path.pop();
+        finished.insert(index);
+        None
+    }
+}
+
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -8,6 +8,8 @@ pub enum Error {
     ParseInt(#[from] std::num::ParseIntError),
     #[error("SerdeJSON error: {0}")]
     Json(#[from] serde_json::Error),
+    #[error("SerdeYAML error: {0}")]
+    Yaml(#[from] serde_yaml::Error),
     #[error("Var error: {0}")]
     Var(#[from] std::env::VarError),
     #[error("IO error: {0}")]
@@ -16,6 +18,16 @@ pub enum Error {
     Plist(#[from] plist::Error),
     #[error("No available formula with the name '{formula}'")]
     FormulaNotFound { formula: String },
+    #[error("Unknown service source '{source_name}'")]
+    UnknownSource { source_name: String },
+    #[error(
+        "Formula '{formula}' is ambiguous, it matches: {}. Use --label to pick one",
+        candidates.join(", ")
+    )]
+    AmbiguousFormula {
+        formula: String,
+        candidates: Vec<String>,
+    },
     #[error("No plist found for formula '{formula}'")]
     PlistNotFound { formula: String },
     #[error("Service '{formula}' failed to start with exit code {code}")]
@@ -26,6 +38,46 @@ pub enum Error {
         pid: i32,
         reason: String,
     },
+    #[error("Service '{formula}' did not exit even after SIGKILL (PIDs {pids:?})")]
+    ServiceRefusedToStop { formula: String, pids: Vec<i32> },
+    #[error("Service '{formula}' is not running")]
+    ServiceNotRunning { formula: String },
+    #[error("Failed to send {signal} to service '{formula}' with PID {pid}: {reason}")]
+    ServiceSignalFailed {
+        formula: String,
+        pid: i32,
+        signal: String,
+        reason: String,
+    },
+    #[error(
+        "Invalid --since '{value}', expected e.g. 15m, 2h, 1d, an RFC 3339 date or a unix timestamp"
+    )]
+    InvalidSince { value: String },
+    #[error("Invalid config {path}, line {line}: `{key}`: {message}")]
+    InvalidConfig {
+        path: String,
+        line: usize,
+        key: String,
+        message: String,
+    },
+    #[error("Dependency cycle: {}", cycle.join(" -> "))]
+    DependencyCycle { cycle: Vec<String> },
+    #[error("Service '{formula}' requires '{dependency}', which is not running")]
+    DependencyNotRunning { formula: String, dependency: String },
+    #[error("No flint.toml or Procfile in {dir} or its parents")]
+    ProjectNotFound { dir: String },
+    #[error("Invalid health check: {reason}")]
+    InvalidHealthCheck { reason: String },
+    #[error("Service '{formula}' did not become healthy: {reason}")]
+    ServiceNotReady { formula: String, reason: String },
+    #[error("Unknown signal '{signal}'")]
+    InvalidSignal { signal: String },
+    #[error("No running process named '{binary}' to adopt for formula '{formula}'")]
+    NoProcessToAdopt { formula: String, binary: String },
+    #[error(
+        "Service '{formula}' is a system daemon and requires root; re-run with sudo or pass --sudo"
+    )]
+    RequiresRoot { formula: String },
     #[error("DeriveBuilder missing required field: {0}")]
     MissingField(String),
     #[error("Program '{program}' not found for formula '{formula}'")]
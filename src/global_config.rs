@@ -0,0 +1,191 @@
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::error::{Error, Result};
+use crate::services::service_process;
+use crate::services::service_settings::Overrides;
+use crate::status_report::OutputFormat;
+
+/// Environment variable pointing to another global config file.
+pub const CONFIG_ENV: &str = "FLINT_CONFIG";
+
+/// Settings shared by every service, read once at startup from
+/// `~/.config/flint/config.toml` or `$FLINT_CONFIG`.
+///
+/// `log_dir` and `stop_timeout` replace flint's built-in defaults, so plists
+/// still take precedence over them; `[services]` is layered over the plists.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GlobalConfig {
+    /// Homebrew prefix used when `$HOMEBREW_PREFIX` is not set.
+    #[serde(default)]
+    homebrew_prefix: Option<PathBuf>,
+
+    /// Directory of the state files, unless `$FLINT_STATE_DIR` is set.
+    #[serde(default)]
+    state_dir: Option<PathBuf>,
+
+    /// Directory of the logs of services that do not name their own.
+    #[serde(default)]
+    log_dir: Option<PathBuf>,
+
+    /// Seconds between the stop signal and SIGKILL when the plist has no `ExitTimeOut`.
+    #[serde(default)]
+    stop_timeout: Option<u64>,
+
+    /// Output format of `status`, `list`, `info` and `config show` without `--format`.
+    #[serde(default)]
+    output_format: Option<OutputFormat>,
+
+    /// Settings applied to every service, over their plists.
+    #[serde(default)]
+    services: Overrides,
+}
+
+impl GlobalConfig {
+    /// `$FLINT_CONFIG`, or `~/.config/flint/config.toml`.
+    pub fn config_path() -> Result<PathBuf> {
+        if let Some(path) = std::env::var_os(CONFIG_ENV).filter(|path| !path.is_empty()) {
+            return Ok(PathBuf::from(path));
+        }
+        let home = std::env::var("HOME")?;
+        Ok(Path::new(&home)
+            .join(".config")
+            .join("flint")
+            .join("config.toml"))
+    }
+
+    /// Loads the global config, or an empty one if the default file does not exist.
+    ///
+    /// A file named by `$FLINT_CONFIG` must exist.
+    pub fn load() -> Result<Self> {
+        let path = Self::config_path()?;
+        if !path.exists() && std::env::var_os(CONFIG_ENV).is_none_or(|path| path.is_empty()) {
+            return Ok(GlobalConfig::default());
+        }
+        Self::parse(&fs::read_to_string(&path)?, &path)
+    }
+
+    /// Parses and validates a config read from `path`, which is only used in errors.
+    pub fn parse(text: &str, path: &Path) -> Result<Self> {
+        let mut config: GlobalConfig = toml::from_str(text).map_err(|err| {
+            let span = err.span().unwrap_or(0..0);
+            config_error(path, text, span, err.message())
+        })?;
+        config.validate(text, path)?;
+
+        config.state_dir = config.state_dir.map(|dir| expand_home(&dir));
+        config.log_dir = config.log_dir.map(|dir| expand_home(&dir));
+        config.homebrew_prefix = config.homebrew_prefix.map(|dir| expand_home(&dir));
+        Ok(config)
+    }
+
+    fn validate(&self, text: &str, path: &Path) -> Result<()> {
+        let error = |table: Option<&str>, key: &str, message: &str| {
+            let span = key_span(text, table, key).unwrap_or(0..0);
+            config_error(path, text, span, message)
+        };
+
+        for (key, dir) in [
+            ("homebrew_prefix", &self.homebrew_prefix),
+            ("state_dir", &self.state_dir),
+            ("log_dir", &self.log_dir),
+        ] {
+            if let Some(dir) = dir
+                && !expand_home(dir).is_absolute()
+            {
+                return Err(error(
+                    None,
+                    key,
+                    "expected an absolute path or one starting with ~/",
+                ));
+            }
+        }
+        if self.stop_timeout == Some(0) {
+            return Err(error(None, "stop_timeout", "expected at least 1 second"));
+        }
+        if let Some(signal) = &self.services.stop_signal
+            && service_process::parse_signal(signal).is_none()
+        {
+            return Err(error(
+                Some("services"),
+                "stop_signal",
+                &format!("unknown signal '{}'", signal),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn homebrew_prefix(&self) -> Option<&Path> {
+        self.homebrew_prefix.as_deref()
+    }
+
+    pub fn state_dir(&self) -> Option<&Path> {
+        self.state_dir.as_deref()
+    }
+
+    pub fn log_dir(&self) -> Option<&Path> {
+        self.log_dir.as_deref()
+    }
+
+    pub fn stop_timeout(&self) -> Option<Duration> {
+        self.stop_timeout.map(Duration::from_secs)
+    }
+
+    pub fn output_format(&self) -> Option<OutputFormat> {
+        self.output_format
+    }
+
+    pub fn services(&self) -> &Overrides {
+        &self.services
+    }
+}
+
+/// Builds an error pointing to the line of `span` in a TOML file and the key defined there.
+pub(crate) fn config_error(path: &Path, text: &str, span: Range<usize>, message: &str) -> Error {
+    let start = span.start.min(text.len());
+    let line = text[..start].matches('\n').count() + 1;
+    let line_text = text.lines().nth(line - 1).unwrap_or_default().trim();
+    let key = match line_text.split_once('=') {
+        Some((key, _)) => key.trim(),
+        None => line_text.trim_matches(|c| c == '[' || c == ']'),
+    };
+    Error::InvalidConfig {
+        path: path.display().to_string(),
+        line,
+        key: key.to_string(),
+        message: message.trim().to_string(),
+    }
+}
+
+/// Span of the line defining `key`, at the top level or in `[table]`.
+fn key_span(text: &str, table: Option<&str>, key: &str) -> Option<Range<usize>> {
+    let mut current_table = None;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if let Some(header) = trimmed.strip_prefix('[').and_then(|h| h.strip_suffix(']')) {
+            current_table = Some(header.trim().to_string());
+        } else if current_table.as_deref() == table
+            && trimmed
+                .split_once('=')
+                .is_some_and(|(name, _)| name.trim() == key)
+        {
+            return Some(offset..offset + line.len());
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// Replaces a leading `~` with `$HOME`.
+pub(crate) fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
@@ -1,28 +1,275 @@
+use std::collections::BTreeMap;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use derive_builder::Builder;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
+use crate::error::Result;
+
+/// launchd's default delay, in seconds, between SIGTERM and SIGKILL.
+pub const DEFAULT_EXIT_TIMEOUT: u64 = 20;
+
+/// launchd's default minimum delay, in seconds, between two launches of a job.
+pub const DEFAULT_THROTTLE_INTERVAL: u64 = 10;
+
+/// Plist keys mapped onto typed fields of `LaunchdConfig`.
+const KNOWN_KEYS: &[&str] = &[
+    "Label",
+    "Disabled",
+    "Program",
+    "ProgramArguments",
+    "EnvironmentVariables",
+    "WorkingDirectory",
+    "RootDirectory",
+    "StandardInPath",
+    "StandardOutPath",
+    "StandardErrorPath",
+    "UserName",
+    "GroupName",
+    "InitGroups",
+    "Umask",
+    "Nice",
+    "ProcessType",
+    "RunAtLoad",
+    "KeepAlive",
+    "StartInterval",
+    "StartCalendarInterval",
+    "WatchPaths",
+    "QueueDirectories",
+    "StartOnMount",
+    "ThrottleInterval",
+    "ExitTimeOut",
+    "TimeOut",
+    "AbandonProcessGroup",
+    "SoftResourceLimits",
+    "HardResourceLimits",
+];
+
+/// Typed view of a launchd property list.
+///
+/// Keys follow the `launchd.plist(5)` vocabulary. Anything flint does not
+/// model explicitly is kept in `extra` so it is never silently lost.
 #[derive(Debug, Deserialize, Clone, Builder)]
 #[builder(build_fn(error = "crate::error::Error"))]
 pub struct LaunchdConfig {
+    #[serde(alias = "Label", default)]
+    #[builder(default)]
+    label: Option<String>,
+
+    #[serde(alias = "Disabled", default)]
+    #[builder(default)]
+    disabled: bool,
+
     #[serde(alias = "Program")]
     program: Option<String>,
 
     #[serde(alias = "ProgramArguments", default)]
     args: Vec<String>,
 
+    #[serde(alias = "EnvironmentVariables", default)]
+    #[builder(default)]
+    environment_variables: BTreeMap<String, String>,
+
     #[serde(alias = "WorkingDirectory", default = "default_dir")]
     working_directory: String,
 
+    #[serde(alias = "RootDirectory", default)]
+    #[builder(default)]
+    root_directory: Option<String>,
+
+    #[serde(alias = "StandardInPath", default)]
+    #[builder(default)]
+    stdin_path: Option<String>,
+
     #[serde(alias = "StandardOutPath", default)]
     stdout_path: Option<String>,
 
     #[serde(alias = "StandardErrorPath", default)]
     stderr_path: Option<String>,
+
+    #[serde(alias = "UserName", default)]
+    #[builder(default)]
+    user_name: Option<String>,
+
+    #[serde(alias = "GroupName", default)]
+    #[builder(default)]
+    group_name: Option<String>,
+
+    #[serde(alias = "InitGroups", default)]
+    #[builder(default)]
+    init_groups: Option<bool>,
+
+    #[serde(alias = "Umask", default)]
+    #[builder(default)]
+    umask: Option<u32>,
+
+    #[serde(alias = "Nice", default)]
+    #[builder(default)]
+    nice: Option<i32>,
+
+    #[serde(alias = "ProcessType", default)]
+    #[builder(default)]
+    process_type: Option<String>,
+
+    #[serde(alias = "RunAtLoad", default)]
+    #[builder(default)]
+    run_at_load: bool,
+
+    #[serde(alias = "KeepAlive", default)]
+    #[builder(default)]
+    keep_alive: Option<KeepAlive>,
+
+    #[serde(alias = "StartInterval", default)]
+    #[builder(default)]
+    start_interval: Option<u64>,
+
+    #[serde(alias = "StartCalendarInterval", default)]
+    #[builder(default)]
+    start_calendar_interval: Option<StartCalendarInterval>,
+
+    #[serde(alias = "WatchPaths", default)]
+    #[builder(default)]
+    watch_paths: Vec<String>,
+
+    #[serde(alias = "QueueDirectories", default)]
+    #[builder(default)]
+    queue_directories: Vec<String>,
+
+    #[serde(alias = "StartOnMount", default)]
+    #[builder(default)]
+    start_on_mount: bool,
+
+    #[serde(alias = "ThrottleInterval", default)]
+    #[builder(default)]
+    throttle_interval: Option<u64>,
+
+    #[serde(alias = "ExitTimeOut", default)]
+    #[builder(default)]
+    exit_timeout: Option<u64>,
+
+    #[serde(alias = "TimeOut", default)]
+    #[builder(default)]
+    timeout: Option<u64>,
+
+    #[serde(alias = "AbandonProcessGroup", default)]
+    #[builder(default)]
+    abandon_process_group: bool,
+
+    #[serde(alias = "SoftResourceLimits", default)]
+    #[builder(default)]
+    soft_resource_limits: Option<ResourceLimits>,
+
+    #[serde(alias = "HardResourceLimits", default)]
+    #[builder(default)]
+    hard_resource_limits: Option<ResourceLimits>,
+
+    /// Keys not modelled above, preserved as raw plist values by `from_bytes`.
+    #[serde(skip)]
+    #[builder(default)]
+    extra: BTreeMap<String, plist::Value>,
+}
+
+/// `KeepAlive` is either a plain boolean or a dictionary of conditions.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum KeepAlive {
+    Always(bool),
+    Conditions(KeepAliveConditions),
+}
+
+/// Conditional form of `KeepAlive`. The job is kept alive if any condition holds.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+pub struct KeepAliveConditions {
+    #[serde(alias = "SuccessfulExit", default)]
+    pub successful_exit: Option<bool>,
+
+    #[serde(alias = "Crashed", default)]
+    pub crashed: Option<bool>,
+
+    #[serde(alias = "NetworkState", default)]
+    pub network_state: Option<bool>,
+
+    #[serde(alias = "PathState", default)]
+    pub path_state: BTreeMap<String, bool>,
+
+    #[serde(alias = "OtherJobEnabled", default)]
+    pub other_job_enabled: BTreeMap<String, bool>,
+
+    #[serde(alias = "AfterInitialDemand", default)]
+    pub after_initial_demand: BTreeMap<String, bool>,
+}
+
+/// `StartCalendarInterval` accepts a single dictionary or an array of them.
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum StartCalendarInterval {
+    Single(CalendarInterval),
+    Multiple(Vec<CalendarInterval>),
+}
+
+/// One calendar entry; missing fields act as wildcards, like cron.
+#[derive(Debug, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct CalendarInterval {
+    #[serde(alias = "Minute", default)]
+    pub minute: Option<u32>,
+
+    #[serde(alias = "Hour", default)]
+    pub hour: Option<u32>,
+
+    #[serde(alias = "Day", default)]
+    pub day: Option<u32>,
+
+    #[serde(alias = "Weekday", default)]
+    pub weekday: Option<u32>,
+
+    #[serde(alias = "Month", default)]
+    pub month: Option<u32>,
+}
+
+/// Resource limits as accepted by `setrlimit(2)`.
+#[derive(Debug, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+    #[serde(alias = "Core", default)]
+    pub core: Option<u64>,
+
+    #[serde(alias = "CPU", default)]
+    pub cpu: Option<u64>,
+
+    #[serde(alias = "Data", default)]
+    pub data: Option<u64>,
+
+    #[serde(alias = "FileSize", default)]
+    pub file_size: Option<u64>,
+
+    #[serde(alias = "MemoryLock", default)]
+    pub memory_lock: Option<u64>,
+
+    #[serde(alias = "NumberOfFiles", default)]
+    pub number_of_files: Option<u64>,
+
+    #[serde(alias = "NumberOfProcesses", default)]
+    pub number_of_processes: Option<u64>,
+
+    #[serde(alias = "ResidentSetSize", default)]
+    pub resident_set_size: Option<u64>,
+
+    #[serde(alias = "Stack", default)]
+    pub stack: Option<u64>,
 }
 
 impl LaunchdConfig {
+    /// Parse a plist document, keeping unknown keys in `extra`.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let dict: plist::Dictionary = plist::from_bytes(data)?;
+        let mut config: LaunchdConfig = plist::from_value(&plist::Value::Dictionary(dict.clone()))?;
+        config.extra = dict
+            .into_iter()
+            .filter(|(key, _)| !KNOWN_KEYS.contains(&key.as_str()))
+            .collect();
+        Ok(config)
+    }
+
     pub fn binary_name(&self) -> String {
         let path_prog = PathBuf::from(self.program());
         path_prog
@@ -32,6 +279,14 @@ impl LaunchdConfig {
             .to_string()
     }
 
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    pub fn disabled(&self) -> bool {
+        self.disabled
+    }
+
     pub fn program(&self) -> &str {
         if let Some(prog) = &self.program {
             prog
@@ -52,10 +307,22 @@ impl LaunchdConfig {
         }
     }
 
+    pub fn environment_variables(&self) -> &BTreeMap<String, String> {
+        &self.environment_variables
+    }
+
     pub fn working_directory(&self) -> &str {
         &self.working_directory
     }
 
+    pub fn root_directory(&self) -> Option<&str> {
+        self.root_directory.as_deref()
+    }
+
+    pub fn stdin_path(&self) -> Option<&str> {
+        self.stdin_path.as_deref()
+    }
+
     pub fn stdout_path(&self) -> Option<&str> {
         self.stdout_path.as_deref()
     }
@@ -63,6 +330,97 @@ impl LaunchdConfig {
     pub fn stderr_path(&self) -> Option<&str> {
         self.stderr_path.as_deref()
     }
+
+    pub fn user_name(&self) -> Option<&str> {
+        self.user_name.as_deref()
+    }
+
+    pub fn group_name(&self) -> Option<&str> {
+        self.group_name.as_deref()
+    }
+
+    pub fn init_groups(&self) -> Option<bool> {
+        self.init_groups
+    }
+
+    pub fn umask(&self) -> Option<u32> {
+        self.umask
+    }
+
+    pub fn nice(&self) -> Option<i32> {
+        self.nice
+    }
+
+    pub fn process_type(&self) -> Option<&str> {
+        self.process_type.as_deref()
+    }
+
+    pub fn run_at_load(&self) -> bool {
+        self.run_at_load
+    }
+
+    pub fn keep_alive(&self) -> Option<&KeepAlive> {
+        self.keep_alive.as_ref()
+    }
+
+    pub fn start_interval(&self) -> Option<u64> {
+        self.start_interval
+    }
+
+    /// Calendar entries, regardless of whether the plist used the single or array form.
+    pub fn start_calendar_intervals(&self) -> &[CalendarInterval] {
+        match &self.start_calendar_interval {
+            Some(StartCalendarInterval::Single(interval)) => std::slice::from_ref(interval),
+            Some(StartCalendarInterval::Multiple(intervals)) => intervals,
+            None => &[],
+        }
+    }
+
+    pub fn watch_paths(&self) -> &[String] {
+        &self.watch_paths
+    }
+
+    pub fn queue_directories(&self) -> &[String] {
+        &self.queue_directories
+    }
+
+    pub fn start_on_mount(&self) -> bool {
+        self.start_on_mount
+    }
+
+    pub fn throttle_interval(&self) -> Option<u64> {
+        self.throttle_interval
+    }
+
+    pub fn exit_timeout(&self) -> Option<u64> {
+        self.exit_timeout
+    }
+
+    /// `ExitTimeOut`, or launchd's default when the plist does not set it.
+    pub fn exit_timeout_or_default(&self) -> Duration {
+        Duration::from_secs(self.exit_timeout.unwrap_or(DEFAULT_EXIT_TIMEOUT))
+    }
+
+    pub fn timeout(&self) -> Option<u64> {
+        self.timeout
+    }
+
+    pub fn abandon_process_group(&self) -> bool {
+        self.abandon_process_group
+    }
+
+    pub fn soft_resource_limits(&self) -> Option<&ResourceLimits> {
+        self.soft_resource_limits.as_ref()
+    }
+
+    pub fn hard_resource_limits(&self) -> Option<&ResourceLimits> {
+        self.hard_resource_limits.as_ref()
+    }
+
+    /// Plist keys flint does not model, keyed by their original name.
+    pub fn extra(&self) -> &BTreeMap<String, plist::Value> {
+        &self.extra
+    }
 }
 
 fn default_dir() -> String {
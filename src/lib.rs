@@ -1,5 +1,11 @@
 pub mod cli;
+pub mod deps;
 pub mod error;
+pub mod global_config;
 pub mod launchd_config;
+pub mod logs;
+pub mod project;
 pub mod service_manager;
 pub mod services;
+pub mod status_report;
+pub mod supervisor;
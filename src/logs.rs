@@ -0,0 +1,282 @@
+use std::fs::{self, File};
+use std::io::{IsTerminal, Read, Seek, SeekFrom};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+use crate::error::{Error, Result};
+
+/// How often followed logs are checked for new output.
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Colors cycled through for the per-service prefixes.
+const PREFIX_COLORS: &[&str] = &["36", "33", "32", "35", "34", "96", "93", "92"];
+
+/// One of the two output streams of a service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    /// Marker shown next to each line, `out` or `err`.
+    pub fn marker(&self) -> &'static str {
+        match self {
+            Stream::Stdout => "out",
+            Stream::Stderr => "err",
+        }
+    }
+}
+
+/// A log file to show, and how to prefix its lines.
+#[derive(Debug, Clone)]
+pub struct LogSource {
+    pub formula: String,
+    pub stream: Stream,
+    pub path: PathBuf,
+}
+
+/// What `flint logs` shows.
+#[derive(Debug, Clone)]
+pub struct LogsOptions {
+    /// Lines shown from the end of each log before following.
+    pub lines: usize,
+    pub follow: bool,
+    /// Skip logs untouched since then, and timestamped lines written before it.
+    pub since: Option<SystemTime>,
+}
+
+/// Parses `--since`: a duration ago such as `90s`, `15m`, `2h` or `1d`, an RFC 3339
+/// date such as `2024-05-01T12:00:00Z`, or a unix timestamp in seconds.
+pub fn parse_since(value: &str) -> Result<SystemTime> {
+    let invalid = || Error::InvalidSince {
+        value: value.to_string(),
+    };
+
+    if let Ok(date) = OffsetDateTime::parse(value, &Rfc3339) {
+        return Ok(date.into());
+    }
+    if let Ok(secs) = value.parse::<u64>() {
+        return Ok(SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+    }
+
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount.parse().map_err(|_| invalid())?;
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3_600,
+        "d" => 86_400,
+        _ => return Err(invalid()),
+    };
+    SystemTime::now()
+        .checked_sub(Duration::from_secs(amount * unit_secs))
+        .ok_or_else(invalid)
+}
+
+/// Prints the last lines of every source, then follows them if asked to.
+///
+/// Sources are shown in order; with `follow`, new lines of all sources are
+/// interleaved as they arrive and rotated logs are reopened transparently.
+pub async fn show(sources: &[LogSource], options: &LogsOptions) -> Result<()> {
+    let prefixes = Prefixes::new(sources);
+
+    for source in sources {
+        if options
+            .since
+            .is_some_and(|since| !modified_since(&source.path, since))
+        {
+            continue;
+        }
+        for line in tail(&source.path, options.lines)? {
+            if is_since(&line, options.since) {
+                println!("{}", prefixes.render(source, &line));
+            }
+        }
+    }
+
+    if !options.follow {
+        return Ok(());
+    }
+    let followers = sources.iter().map(|s| Follower::at_end(&s.path)).collect();
+    follow(sources, followers).await
+}
+
+/// Prints the new lines of every source as they arrive, `followers` being the
+/// followers of `sources` in the same order. Never returns unless reading fails.
+pub async fn follow(sources: &[LogSource], mut followers: Vec<Follower>) -> Result<()> {
+    let prefixes = Prefixes::new(sources);
+    loop {
+        for (source, follower) in sources.iter().zip(&mut followers) {
+            for line in follower.poll()? {
+                println!("{}", prefixes.render(source, &line));
+            }
+        }
+        tokio::time::sleep(FOLLOW_POLL_INTERVAL).await;
+    }
+}
+
+/// The last `count` lines of a log, or none if it does not exist yet.
+pub fn tail(path: &Path, count: usize) -> Result<Vec<String>> {
+    let contents = match fs::read(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let text = String::from_utf8_lossy(&contents);
+    let lines: Vec<&str> = text.lines().collect();
+    let start = lines.len().saturating_sub(count);
+    Ok(lines[start..].iter().map(|line| line.to_string()).collect())
+}
+
+fn modified_since(path: &Path, since: SystemTime) -> bool {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .is_ok_and(|modified| modified >= since)
+}
+
+/// Whether a line was written after `since`, judging by a leading RFC 3339
+/// timestamp or the `ts` of a JSON line; lines without one are always kept.
+fn is_since(line: &str, since: Option<SystemTime>) -> bool {
+    let Some(since) = since else {
+        return true;
+    };
+    match line_timestamp(line) {
+        Some(written) => SystemTime::from(written) >= since,
+        None => true,
+    }
+}
+
+fn line_timestamp(line: &str) -> Option<OffsetDateTime> {
+    if line.starts_with('{') {
+        let record: serde_json::Value = serde_json::from_str(line).ok()?;
+        return OffsetDateTime::parse(record["ts"].as_str()?, &Rfc3339).ok();
+    }
+    let first_word = line.split_whitespace().next()?;
+    OffsetDateTime::parse(first_word, &Rfc3339).ok()
+}
+
+/// Reads the lines appended to a log, following it across rotations.
+///
+/// Rotation is noticed when the path points to a new file or shrinks; the rest
+/// of the old file is read first, then the new one from its start.
+pub struct Follower {
+    path: PathBuf,
+    file: Option<File>,
+    inode: u64,
+    position: u64,
+    partial: String,
+}
+
+impl Follower {
+    /// Starts following from the current end of `path`.
+    pub fn at_end(path: &Path) -> Self {
+        let mut follower = Follower {
+            path: path.to_path_buf(),
+            file: None,
+            inode: 0,
+            position: 0,
+            partial: String::new(),
+        };
+        if let Ok(file) = File::open(path) {
+            follower.inode = file.metadata().map(|m| m.ino()).unwrap_or_default();
+            follower.position = file.metadata().map(|m| m.len()).unwrap_or_default();
+            follower.file = Some(file);
+        }
+        follower
+    }
+
+    /// Complete lines written since the last poll.
+    pub fn poll(&mut self) -> Result<Vec<String>> {
+        let mut lines = self.read_new()?;
+
+        let replaced = match fs::metadata(&self.path) {
+            Ok(metadata) => {
+                self.file.is_none()
+                    || metadata.ino() != self.inode
+                    || metadata.len() < self.position
+            }
+            Err(_) => false,
+        };
+        if replaced && let Ok(file) = File::open(&self.path) {
+            // The old file is finished, even if its last line has no newline.
+            if !self.partial.is_empty() {
+                lines.push(std::mem::take(&mut self.partial));
+            }
+            self.inode = file.metadata()?.ino();
+            self.position = 0;
+            self.file = Some(file);
+            lines.extend(self.read_new()?);
+        }
+        Ok(lines)
+    }
+
+    fn read_new(&mut self) -> Result<Vec<String>> {
+        let Some(file) = &mut self.file else {
+            return Ok(Vec::new());
+        };
+        file.seek(SeekFrom::Start(self.position))?;
+        let mut buffer = Vec::new();
+        self.position += file.read_to_end(&mut buffer)? as u64;
+        self.partial += &String::from_utf8_lossy(&buffer);
+
+        let Some(last_newline) = self.partial.rfind('\n') else {
+            return Ok(Vec::new());
+        };
+        let complete: String = self.partial.drain(..=last_newline).collect();
+        Ok(complete.lines().map(str::to_string).collect())
+    }
+}
+
+/// Aligned `<formula> <out|err> |` prefixes, colored per service on a terminal.
+struct Prefixes {
+    width: usize,
+    formulas: Vec<String>,
+    color: bool,
+}
+
+impl Prefixes {
+    fn new(sources: &[LogSource]) -> Self {
+        let mut formulas: Vec<String> = Vec::new();
+        for source in sources {
+            if !formulas.contains(&source.formula) {
+                formulas.push(source.formula.clone());
+            }
+        }
+        Prefixes {
+            width: formulas.iter().map(String::len).max().unwrap_or_default(),
+            formulas,
+            color: std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+        }
+    }
+
+    fn render(&self, source: &LogSource, line: &str) -> String {
+        let prefix = format!(
+            "{:<width$} {} |",
+            source.formula,
+            source.stream.marker(),
+            width = self.width
+        );
+        if !self.color {
+            return format!("{} {}", prefix, line);
+        }
+        let index = self
+            .formulas
+            .iter()
+            .position(|formula| *formula == source.formula)
+            .unwrap_or_default();
+        format!(
+            "\x1b[{}m{}\x1b[0m {}",
+            PREFIX_COLORS[index % PREFIX_COLORS.len()],
+            prefix,
+            line
+        )
+    }
+}
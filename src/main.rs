@@ -1,42 +1,200 @@
-use crate::cli::{Cli, Commands};
-use crate::error::Result;
-use crate::service_manager::ServiceManager;
+use std::os::unix::process::CommandExt;
+use std::process::Command;
+
 use clap::Parser;
-mod cli;
-mod error;
-mod launchd_config;
-mod service_manager;
-mod services;
+use flint::cli::{Cli, Commands, ConfigCommand};
+use flint::deps::DependencyGraph;
+use flint::error::{Error, Result};
+use flint::global_config::GlobalConfig;
+use flint::logs::{self, LogSource, LogsOptions, Stream};
+use flint::project::Project;
+use flint::service_manager::ServiceManager;
+use flint::status_report::{OutputFormat, render_all, render_info, render_one, render_settings};
+use flint::supervisor::Supervisor;
 
 #[tokio::main]
 async fn main() {
     // init tracing
     tracing_subscriber::fmt::init();
 
-    if let Err(err) = try_main().await {
-        eprintln!("❌ Error: {}", err);
-        std::process::exit(1);
+    let cli = Cli::parse();
+    let sudo = cli.sudo;
+    let err = match try_main(cli).await {
+        Ok(()) => return,
+        Err(Error::RequiresRoot { .. }) if sudo => reexec_with_sudo(),
+        Err(err) => err,
+    };
+    eprintln!("❌ Error: {}", err);
+    std::process::exit(1);
+}
+
+/// Replaces the current process with the same flint invocation run through sudo.
+///
+/// Only returns if sudo could not be executed.
+fn reexec_with_sudo() -> Error {
+    let err = match std::env::current_exe() {
+        Ok(exe) => Command::new("sudo")
+            .arg(exe)
+            .args(std::env::args_os().skip(1))
+            .exec(),
+        Err(err) => err,
+    };
+    Error::Io(err)
+}
+
+fn get_manager(
+    service_name: String,
+    source: Option<&str>,
+    label: bool,
+    global: &GlobalConfig,
+) -> Result<ServiceManager> {
+    if label {
+        ServiceManager::with_label(service_name, source, global)
+    } else {
+        ServiceManager::with_source(service_name, source, global)
     }
 }
 
-fn get_manager(service_name: String) -> Result<ServiceManager> {
-    ServiceManager::new(service_name)
+/// The project of the current directory or its closest parent that has one.
+fn current_project() -> Result<Project> {
+    let dir = std::env::current_dir()?;
+    Project::find(&dir)?.ok_or_else(|| Error::ProjectNotFound {
+        dir: dir.display().to_string(),
+    })
 }
 
-async fn try_main() -> Result<()> {
-    let cli = Cli::parse();
+async fn try_main(cli: Cli) -> Result<()> {
+    let Cli {
+        source,
+        label,
+        command,
+        ..
+    } = cli;
+    let source = source.as_deref();
+    let global = GlobalConfig::load()?;
+    let get_manager = |name: String| get_manager(name, source, label, &global);
+    let output_format =
+        |format: Option<OutputFormat>| format.or(global.output_format()).unwrap_or_default();
 
-    match cli.command {
-        Commands::Start { formula } => get_manager(formula)?.start()?,
-        Commands::Stop { formula } => get_manager(formula)?.stop()?,
-        Commands::Status { formula } => match formula {
-            Some(name) => {
-                get_manager(name)?.state()?;
+    match command {
+        Commands::Start {
+            formula,
+            adopt,
+            wait,
+            overrides,
+        } => {
+            let manager = get_manager(formula)?.with_overrides(&overrides.overrides())?;
+            if adopt {
+                manager.reconcile(true)?;
             }
-            None => {
-                ServiceManager::states()?;
+            DependencyGraph::resolve(vec![manager])?.start(wait)?
+        }
+        Commands::Stop { formula, overrides } => get_manager(formula)?
+            .with_overrides(&overrides.overrides())?
+            .stop()?,
+        Commands::Restart { formula, overrides } => get_manager(formula)?
+            .with_overrides(&overrides.overrides())?
+            .restart()?,
+        Commands::Reload { formula } => get_manager(formula)?.reload()?,
+        Commands::Kill { formula, signal } => get_manager(formula)?.kill(&signal)?,
+        Commands::Adopt { formula } => get_manager(formula)?.adopt()?,
+        Commands::Status {
+            formula,
+            adopt,
+            format,
+        } => match formula {
+            Some(name) => {
+                let manager = get_manager(name)?;
+                if adopt {
+                    manager.reconcile(true)?;
+                }
+                println!("{}", render_one(&manager.report()?, output_format(format))?)
             }
+            None => println!(
+                "{}",
+                render_all(
+                    &ServiceManager::reports(source, &global)?,
+                    output_format(format)
+                )?
+            ),
         },
+        Commands::Info { formula, format } => println!(
+            "{}",
+            render_info(&get_manager(formula)?.info()?, output_format(format))?
+        ),
+        Commands::List { format } => {
+            println!(
+                "{}",
+                render_all(
+                    &ServiceManager::reports(source, &global)?,
+                    output_format(format)
+                )?
+            )
+        }
+        Commands::Logs {
+            formulas,
+            lines,
+            follow,
+            stdout,
+            stderr,
+            since,
+            ..
+        } => {
+            let streams = match (stdout, stderr) {
+                (true, _) => vec![Stream::Stdout],
+                (_, true) => vec![Stream::Stderr],
+                _ => vec![Stream::Stdout, Stream::Stderr],
+            };
+            let mut sources = Vec::new();
+            for formula in formulas {
+                let manager = get_manager(formula)?;
+                for stream in &streams {
+                    let path = match stream {
+                        Stream::Stdout => manager.log().stdout_path(),
+                        Stream::Stderr => manager.log().stderr_path(),
+                    };
+                    sources.push(LogSource {
+                        formula: manager.formula().to_string(),
+                        stream: *stream,
+                        path: path.into(),
+                    });
+                }
+            }
+            let options = LogsOptions {
+                lines,
+                follow,
+                since: since.as_deref().map(logs::parse_since).transpose()?,
+            };
+            logs::show(&sources, &options).await?
+        }
+        Commands::Supervise {
+            formulas,
+            overrides,
+        } => {
+            Supervisor::new(formulas, source, label, &global, &overrides.overrides())?
+                .run()
+                .await?
+        }
+        Commands::Deps { formula, format } => println!(
+            "{}",
+            DependencyGraph::resolve(vec![get_manager(formula)?])?.render(format)
+        ),
+        Commands::Up { services } => current_project()?.up(&services, &global).await?,
+        Commands::Down { services } => current_project()?.down(&services, &global)?,
+        Commands::Config {
+            command:
+                ConfigCommand::Show {
+                    formula,
+                    overrides,
+                    format,
+                },
+        } => {
+            let manager = get_manager(formula)?.with_overrides(&overrides.overrides())?;
+            println!(
+                "{}",
+                render_settings(manager.settings(), output_format(format))?
+            )
+        }
     }
 
     Ok(())
@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use serde::Deserialize;
+
+use crate::deps::DependencyGraph;
+use crate::error::{Error, Result};
+use crate::global_config::{GlobalConfig, config_error};
+use crate::logs::{self, Follower, LogSource, Stream};
+use crate::service_manager::ServiceManager;
+use crate::services::service_native::NativeDefinition;
+use crate::services::service_source::{ProjectSource, ServiceSource};
+use crate::supervisor::wait_for_shutdown_signal;
+
+/// Project files looked for in each directory, the first found wins.
+pub const PROJECT_FILES: &[&str] = &["flint.toml", "Procfile"];
+
+/// A set of services defined by a repository, in a `flint.toml` or `Procfile`.
+///
+/// Services are namespaced by the project name: `web` of project `shop` is the
+/// formula `shop.web` with the label `flint.shop.web`, and runs from the project
+/// root unless it sets its own working directory.
+#[derive(Debug, Clone)]
+pub struct Project {
+    name: String,
+    root: PathBuf,
+    file: PathBuf,
+    services: Vec<(String, NativeDefinition)>,
+}
+
+/// Layout of `flint.toml`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProjectFile {
+    /// Namespace of the services, the name of the project directory when unset.
+    #[serde(default)]
+    name: Option<String>,
+
+    /// Native definitions keyed by service name.
+    #[serde(default)]
+    services: BTreeMap<String, NativeDefinition>,
+}
+
+impl Project {
+    /// The project of `dir` or its closest parent that has a project file.
+    pub fn find(dir: &Path) -> Result<Option<Self>> {
+        for dir in dir.ancestors() {
+            for file_name in PROJECT_FILES {
+                let file = dir.join(file_name);
+                if file.is_file() {
+                    return Self::load(&file).map(Some);
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Reads a `flint.toml`, or a Procfile for any other file name.
+    pub fn load(file: &Path) -> Result<Self> {
+        let text = fs::read_to_string(file)?;
+        let root = file
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default()
+            .canonicalize()?;
+        let (name, services) = if file
+            .file_name()
+            .is_some_and(|name| name == PROJECT_FILES[0])
+        {
+            let project: ProjectFile = toml::from_str(&text).map_err(|err| {
+                config_error(file, &text, err.span().unwrap_or(0..0), err.message())
+            })?;
+            (project.name, project.services.into_iter().collect())
+        } else {
+            (None, parse_procfile(&text, file)?)
+        };
+
+        let name = name.unwrap_or_else(|| {
+            let dir_name = root.file_name().unwrap_or_default().to_string_lossy();
+            namespace(&dir_name)
+        });
+        let services = services
+            .into_iter()
+            .map(|(service, definition): (String, NativeDefinition)| {
+                let label = format!("flint.{}.{}", name, service);
+                (service, definition.or_defaults(label, &root))
+            })
+            .collect();
+        Ok(Project {
+            name,
+            root,
+            file: file.to_path_buf(),
+            services,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+
+    /// Service names, in the order of the project file.
+    pub fn service_names(&self) -> impl Iterator<Item = &str> {
+        self.services.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// The formula of service `name`, `<project>.<name>`.
+    pub fn formula(&self, name: &str) -> String {
+        format!("{}.{}", self.name, name)
+    }
+
+    /// The definition behind `formula`, if it is one of this project's.
+    pub fn definition(&self, formula: &str) -> Option<&NativeDefinition> {
+        let name = formula.strip_prefix(&self.name)?.strip_prefix('.')?;
+        self.services
+            .iter()
+            .find(|(service, _)| service == name)
+            .map(|(_, definition)| definition)
+    }
+
+    /// Managers of the services named in `names`, or of every service when empty.
+    pub fn managers(&self, names: &[String], global: &GlobalConfig) -> Result<Vec<ServiceManager>> {
+        let source = ProjectSource::new(self.clone());
+        let names: Vec<&str> = if names.is_empty() {
+            self.service_names().collect()
+        } else {
+            names.iter().map(String::as_str).collect()
+        };
+        names
+            .into_iter()
+            .map(|name| {
+                let service =
+                    source
+                        .resolve(&self.formula(name))?
+                        .ok_or_else(|| Error::FormulaNotFound {
+                            formula: name.to_string(),
+                        })?;
+                ServiceManager::from_resolved(service, global)
+            })
+            .collect()
+    }
+
+    /// Starts the services after the ones they require, then prints their output
+    /// until SIGINT or SIGTERM.
+    ///
+    /// On the way out, the project services are stopped along with the services
+    /// started for them, in reverse order.
+    pub async fn up(&self, names: &[String], global: &GlobalConfig) -> Result<()> {
+        let graph = DependencyGraph::resolve(self.managers(names, global)?)?;
+        let order = graph.start_order()?;
+        let mut sources = Vec::new();
+        for manager in &order {
+            let name = manager.formula();
+            let name = name
+                .strip_prefix(&format!("{}.", self.name))
+                .unwrap_or(name);
+            for (stream, path) in [
+                (Stream::Stdout, manager.log().stdout_path()),
+                (Stream::Stderr, manager.log().stderr_path()),
+            ] {
+                sources.push(LogSource {
+                    formula: name.to_string(),
+                    stream,
+                    path: path.into(),
+                });
+            }
+        }
+        // Follow from before the first start so no early output is missed.
+        let followers = sources.iter().map(|s| Follower::at_end(&s.path)).collect();
+
+        let mut started = Vec::new();
+        for manager in order {
+            if let Err(e) = start_in_group(&graph, manager, &mut started) {
+                stop_all(started.into_iter().rev());
+                return Err(e);
+            }
+        }
+
+        tokio::select! {
+            result = logs::follow(&sources, followers) => result?,
+            _ = wait_for_shutdown_signal() => {}
+        }
+        stop_all(started.into_iter().rev());
+        Ok(())
+    }
+
+    /// Stops the services, each before the services it depends on.
+    pub fn down(&self, names: &[String], global: &GlobalConfig) -> Result<()> {
+        let graph = DependencyGraph::resolve(self.managers(names, global)?)?;
+        stop_all(graph.stop_order()?.into_iter());
+        Ok(())
+    }
+}
+
+/// Starts a service for `up` once the ones it requires run, and waits for it to
+/// become healthy if others require it.
+///
+/// Project services, and the services `up` started itself, are added to `started`
+/// so they are stopped on the way out.
+fn start_in_group<'a>(
+    graph: &DependencyGraph,
+    manager: &'a ServiceManager,
+    started: &mut Vec<&'a ServiceManager>,
+) -> Result<()> {
+    graph.check_requirements(manager)?;
+    let child = manager.start_attached()?;
+    if child.is_some() || manager.service().source() == ProjectSource::NAME {
+        started.push(manager);
+    }
+    // Reap the child as soon as it exits, or stopping it would wait on a zombie.
+    if let Some(mut child) = child {
+        thread::spawn(move || child.wait());
+    }
+    if graph.is_required(manager) {
+        manager.wait_until_ready(None)?;
+    }
+    Ok(())
+}
+
+/// Stops every service, reporting failures without giving up on the others.
+fn stop_all<'a>(managers: impl Iterator<Item = &'a ServiceManager>) {
+    for manager in managers {
+        if let Err(e) = manager.stop() {
+            eprintln!("❌ Error: {}", e);
+        }
+    }
+}
+
+/// Parses `name: command` lines, skipping blank lines and `#` comments.
+fn parse_procfile(text: &str, path: &Path) -> Result<Vec<(String, NativeDefinition)>> {
+    let mut services: Vec<(String, NativeDefinition)> = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |key: &str, message: &str| Error::InvalidConfig {
+            path: path.display().to_string(),
+            line: index + 1,
+            key: key.to_string(),
+            message: message.to_string(),
+        };
+        let Some((name, command)) = line.split_once(':') else {
+            return Err(error(line, "expected `name: command`"));
+        };
+        let (name, command) = (name.trim(), command.trim());
+        if name.is_empty() || !name.chars().all(is_name_char) {
+            return Err(error(name, "expected letters, digits, `-` or `_`"));
+        }
+        if command.is_empty() {
+            return Err(error(name, "missing command"));
+        }
+        if services.iter().any(|(seen, _)| seen == name) {
+            return Err(error(name, "defined twice"));
+        }
+        services.push((name.to_string(), NativeDefinition::shell(command)));
+    }
+    Ok(services)
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '_'
+}
+
+/// A directory name made safe for formulas and labels.
+fn namespace(dir_name: &str) -> String {
+    dir_name
+        .chars()
+        .map(|c| {
+            if is_name_char(c) {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
@@ -1,72 +1,201 @@
-use std::process::Command;
+use std::fs::File;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::Path;
+use std::process::{Child, Command};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use nix::libc::kill;
+use nix::libc::{SIGHUP, SIGKILL, c_int};
+use nix::unistd::Uid;
 
 use crate::error::{Error, Result};
+use crate::global_config::GlobalConfig;
 use crate::launchd_config::LaunchdConfig;
-use crate::services::service_homebrew::ServiceHomebrew;
+use crate::project::Project;
+use crate::services::service_config::ServiceConfig;
+use crate::services::service_health::Health;
 use crate::services::service_log::ServiceLog;
-use crate::services::service_state::{ServiceState, ServiceStatus};
-use crate::services::service_user_agent::ServiceUserAgent;
+use crate::services::service_native::NativeDefinition;
+use crate::services::service_process::{self, SignalTarget};
+use crate::services::service_settings::{Origin, Overrides, ServiceSettings};
+use crate::services::service_source::{
+    ProjectSource, ResolvedService, ServiceSource, ServiceSources,
+};
+use crate::services::service_state::{HistoryEntry, ServiceState, ServiceStatus, StartedBy};
+use crate::status_report::StatusReport;
 
-pub enum ServiceType {
-    Homebrew(ServiceHomebrew),
-    UserAgent(ServiceUserAgent),
-}
+/// How long to wait for processes to disappear after SIGKILL.
+const KILL_GRACE_PERIOD: Duration = Duration::from_secs(2);
 
-impl ServiceType {
-    pub fn formula(&self) -> &str {
-        match self {
-            ServiceType::Homebrew(svc) => svc.formula(),
-            ServiceType::UserAgent(svc) => svc.formula(),
-        }
-    }
+/// How often `start --wait` probes a service that is not healthy yet.
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(250);
 
-    fn formula_plist_path(&self) -> &str {
-        match self {
-            ServiceType::Homebrew(svc) => svc.formula_plist_path(),
-            ServiceType::UserAgent(svc) => svc.formula_plist_path(),
-        }
-    }
-}
+/// How long `kill` waits for the service to exit before leaving it marked running.
+const SIGNAL_SETTLE_DELAY: Duration = Duration::from_millis(500);
 
 pub struct ServiceManager {
-    service: ServiceType,
+    global: GlobalConfig,
+    service: ResolvedService,
     launchd: LaunchdConfig,
+    /// Settings of a native definition that have no plist equivalent.
+    definition: Overrides,
+    config: ServiceConfig,
+    settings: ServiceSettings,
     log: ServiceLog,
     state: ServiceState,
 }
 
 impl ServiceManager {
     pub fn new(formula: String) -> Result<Self> {
-        let service = if let Ok(homebrew) = ServiceHomebrew::new(formula.clone()) {
-            ServiceType::Homebrew(homebrew)
-        } else if let Ok(user_agent) = ServiceUserAgent::new(formula.clone()) {
-            ServiceType::UserAgent(user_agent)
-        } else {
-            return Err(Error::FormulaNotFound { formula });
-        };
+        Self::with_source(formula, None, &GlobalConfig::load()?)
+    }
+
+    /// Resolves `formula` across the configured sources, or only in `source` if given.
+    pub fn with_source(
+        formula: String,
+        source: Option<&str>,
+        global: &GlobalConfig,
+    ) -> Result<Self> {
+        let service = ServiceSources::from_env(global)?.resolve(&formula, source)?;
+        Self::from_resolved(service, global)
+    }
 
-        let data = std::fs::read(&service.formula_plist_path())?;
-        let launchd_service = plist::from_bytes::<LaunchdConfig>(data.as_slice())?;
-        let log = ServiceLog::new(formula.clone(), &launchd_service)?;
-        let stats = ServiceState::new(formula.clone(), &launchd_service);
+    /// Resolves the service whose launchd `Label` is exactly `label`.
+    pub fn with_label(label: String, source: Option<&str>, global: &GlobalConfig) -> Result<Self> {
+        let service = ServiceSources::from_env(global)?.resolve_label(&label, source)?;
+        Self::from_resolved(service, global)
+    }
 
-        return Ok(ServiceManager {
+    /// Builds a manager for a service already located by a `ServiceSource`.
+    pub fn from_resolved(service: ResolvedService, global: &GlobalConfig) -> Result<Self> {
+        let formula = service.formula().to_string();
+        let (launchd_service, definition) = load_definition(&service)?;
+        let config = ServiceConfig::load(&formula)?;
+        let settings = resolve_settings(
+            &formula,
+            &launchd_service,
+            &definition,
+            global,
+            &config,
+            &Overrides::default(),
+        )?;
+        let log = ServiceLog::from_settings(&settings, &config);
+        let stats = ServiceState::new(service.label(), &launchd_service)
+            .with_alias(&formula)
+            .with_state_dir(global.state_dir());
+
+        Ok(ServiceManager {
+            global: global.clone(),
             service,
             launchd: launchd_service,
+            definition,
+            config,
+            settings,
             log,
             state: stats,
-        });
+        })
+    }
+
+    /// Resolves a service named in `requires` or `after`: a service of the same
+    /// project first, then any service by formula or label.
+    pub(crate) fn resolve_dependency(&self, name: &str) -> Result<ServiceManager> {
+        if self.service.source() == ProjectSource::NAME {
+            let project = Project::load(Path::new(self.service.formula_plist_path()))?;
+            let formula = project.formula(name);
+            if let Some(service) = ProjectSource::new(project).resolve(&formula)? {
+                return Self::from_resolved(service, &self.global);
+            }
+        }
+        Self::with_source(name.to_string(), None, &self.global)
+    }
+
+    /// Applies command-line overrides on top of the plist and per-service config.
+    pub fn with_overrides(mut self, overrides: &Overrides) -> Result<Self> {
+        self.settings = resolve_settings(
+            self.service.formula(),
+            &self.launchd,
+            &self.definition,
+            &self.global,
+            &self.config,
+            overrides,
+        )?;
+        self.log = ServiceLog::from_settings(&self.settings, &self.config);
+        Ok(self)
     }
 
     pub fn start(&self) -> Result<()> {
-        let state = self.state.read_state()?;
+        self.start_as(StartedBy::Start).map(drop)
+    }
+
+    /// Starts the service, then waits until it passes its health check.
+    pub fn start_and_wait(&self) -> Result<()> {
+        let child = self.start_as(StartedBy::Start)?;
+        self.wait_until_ready(child)
+    }
+
+    /// Waits up to the `start_timeout` of the health check for the service to
+    /// pass it, failing early if the service exits.
+    ///
+    /// `child` is the process just started, if flint still owns it. Services
+    /// without a health check are ready as soon as they run.
+    pub(crate) fn wait_until_ready(&self, mut child: Option<Child>) -> Result<()> {
+        let Some(check) = &self.settings.health_check.value else {
+            return Ok(());
+        };
+        let deadline = Instant::now() + check.start_timeout();
+        loop {
+            if let Some(status) = child.as_mut().map(Child::try_wait).transpose()?.flatten() {
+                self.state.mark_exited(status.code(), status.signal())?;
+                return Err(Error::ServiceFailedToStart {
+                    formula: self.service.formula().to_string(),
+                    code: status.code().unwrap_or(-1),
+                });
+            }
+            if child.is_none() && self.state.reconcile(false)?.status() != &ServiceStatus::Running {
+                return Err(Error::ServiceNotRunning {
+                    formula: self.service.formula().to_string(),
+                });
+            }
+            match check.check() {
+                Ok(()) => {
+                    println!("Service '{}' is healthy.", self.service.formula());
+                    return Ok(());
+                }
+                Err(reason) if Instant::now() >= deadline => {
+                    return Err(Error::ServiceNotReady {
+                        formula: self.service.formula().to_string(),
+                        reason,
+                    });
+                }
+                Err(_) => thread::sleep(READY_POLL_INTERVAL),
+            }
+        }
+    }
+
+    /// Runs the health check of a running service, `None` if it has none.
+    pub fn health(&self) -> Option<Health> {
+        let check = self.settings.health_check.value.as_ref()?;
+        Some(match check.check() {
+            Ok(()) => Health::Healthy,
+            Err(_) => Health::Unhealthy,
+        })
+    }
+
+    /// Starts the service like `start`, for a flint process that stays around.
+    ///
+    /// Returns the child, which the caller must reap once it exits, or `None`
+    /// if the service was already running.
+    pub(crate) fn start_attached(&self) -> Result<Option<Child>> {
+        self.start_as(StartedBy::Up)
+    }
+
+    /// Starts the service, recording `started_by` as the command that started it.
+    fn start_as(&self, started_by: StartedBy) -> Result<Option<Child>> {
+        self.ensure_privileges()?;
+        let state = self.state.reconcile(false)?;
         if state.status() == &ServiceStatus::Running {
             println!("Service '{}' is already running.", self.service.formula());
-            return Ok(());
+            return Ok(None);
         }
 
         if !self.launchd.is_program_exist() {
@@ -75,17 +204,14 @@ impl ServiceManager {
                 program: self.launchd.program().to_string(),
             });
         }
-        self.log.create_log_dirs()?;
-        let file_stdout = std::fs::File::create(&self.log.stdout_path())?;
-        let file_stderr = std::fs::File::create(&self.log.stderr_path())?;
+        let (file_stdout, file_stderr) = self.open_log_files()?;
         println!(
             "Logging into paths:\n{}\n{}",
             self.log.stdout_path(),
             self.log.stderr_path()
         );
-        let mut child = Command::new(self.launchd.program())
-            .args(self.launchd.args())
-            .current_dir(self.launchd.working_directory())
+        let mut child = self
+            .command()
             .stdout(file_stdout)
             .stderr(file_stderr)
             .spawn()?;
@@ -95,21 +221,90 @@ impl ServiceManager {
         match child.try_wait()? {
             Some(status) => {
                 println!("Service exited early with status: {}", status);
+                self.state.mark_exited(status.code(), status.signal())?;
                 Err(Error::ServiceFailedToStart {
                     formula: self.service.formula().to_string(),
                     code: status.code().unwrap_or(-1),
                 })
             }
             None => {
-                let pids = self.state.search_pids()?;
-                self.state.mark_running(pids.clone())?;
+                self.state.mark_spawned(child.id() as i32, started_by)?;
                 println!("Service '{}' started", self.service.formula());
-                Ok(())
+                Ok(Some(child))
             }
         }
     }
 
+    /// Fails early when the service needs root and flint is not running as root.
+    pub(crate) fn ensure_privileges(&self) -> Result<()> {
+        if self.service.requires_root() && !Uid::effective().is_root() {
+            return Err(Error::RequiresRoot {
+                formula: self.service.formula().to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Opens the stdout/stderr logs for appending, rotating them first if due.
+    ///
+    /// The files are handed to the child, so they only rotate between runs.
+    pub(crate) fn open_log_files(&self) -> Result<(File, File)> {
+        self.log.create_log_dirs()?;
+        Ok((self.log.open_stdout()?, self.log.open_stderr()?))
+    }
+
+    pub fn log(&self) -> &ServiceLog {
+        &self.log
+    }
+
+    /// Builds the command for the service: program, args, working directory and environment.
+    ///
+    /// The effective environment variables are applied on top of either flint's
+    /// environment or an empty one. The child leads its own process group so it
+    /// can be tracked and signalled as a whole.
+    pub(crate) fn command(&self) -> Command {
+        let mut command = Command::new(&self.settings.program.value);
+        command
+            .args(&self.settings.args.value)
+            .current_dir(&self.settings.working_directory.value)
+            .process_group(0);
+
+        if self.config.clear_environment() {
+            command.env_clear();
+        }
+        command.envs(self.settings.environment());
+        command
+    }
+
+    /// Takes over processes started outside flint by matching the binary name.
+    ///
+    /// Name matching can catch unrelated processes, which is why it is never done
+    /// implicitly and the adopted PIDs are printed for review.
+    pub fn adopt(&self) -> Result<()> {
+        self.ensure_privileges()?;
+        let pids = self.state.search_pids()?;
+        if pids.is_empty() {
+            return Err(Error::NoProcessToAdopt {
+                formula: self.service.formula().to_string(),
+                binary: self.launchd.binary_name(),
+            });
+        }
+
+        self.state.mark_running(pids.clone())?;
+        println!(
+            "Service '{}' adopted PIDs {:?}.",
+            self.service.formula(),
+            pids
+        );
+        Ok(())
+    }
+
+    /// Stops the service, escalating from the stop signal (SIGTERM by default) to SIGKILL.
+    ///
+    /// The whole process group is signalled so forked workers go down too. After
+    /// the stop signal, flint waits up to the effective `exit_timeout`.
     pub fn stop(&self) -> Result<()> {
+        self.ensure_privileges()?;
         if self.state.is_managed_by_launchctl()? {
             let _ = Command::new("launchctl")
                 .arg("unload")
@@ -117,70 +312,252 @@ impl ServiceManager {
                 .status();
         }
 
-        for pid in self.state.read_state()?.pids() {
-            if 0 != unsafe { kill(*pid, 0) } {
-                // PID does not exist and may have already exited
-                continue;
-            }
-            let ret = unsafe { kill(*pid, nix::libc::SIGTERM) };
-            if ret != 0 {
-                return Err(Error::ServiceFailedToStop {
+        let targets = self.state.read_state()?.signal_targets();
+        let timeout = self.settings.exit_timeout();
+
+        self.stop_signal_all(&targets, self.settings.stop_signal_number())?;
+        if !service_process::wait_for_exit(&targets, timeout) {
+            println!(
+                "Service '{}' still running after {}s, sending SIGKILL.",
+                self.service.formula(),
+                timeout.as_secs()
+            );
+            self.stop_signal_all(&targets, SIGKILL)?;
+            if !service_process::wait_for_exit(&targets, KILL_GRACE_PERIOD) {
+                return Err(Error::ServiceRefusedToStop {
                     formula: self.service.formula().to_string(),
-                    pid: *pid,
-                    reason: std::io::Error::last_os_error().to_string(),
+                    pids: targets.iter().map(SignalTarget::pid).collect(),
                 });
             }
         }
 
-        thread::sleep(Duration::from_millis(500));
         self.state.mark_stopped()?;
         println!("Service '{}' stopped successfully.", self.service.formula());
         Ok(())
     }
 
-    fn print_state(&self) -> Result<()> {
+    fn stop_signal_all(&self, targets: &[SignalTarget], signal: c_int) -> Result<()> {
+        service_process::signal_all(targets, signal).map_err(|(pid, err)| {
+            Error::ServiceFailedToStop {
+                formula: self.service.formula().to_string(),
+                pid,
+                reason: err.to_string(),
+            }
+        })
+    }
+
+    /// Stops then starts the service, reusing the plist and overrides resolved by `new`.
+    pub fn restart(&self) -> Result<()> {
+        self.stop()?;
+        self.start_as(StartedBy::Restart).map(drop)
+    }
+
+    /// Asks the service to reload its configuration without restarting it.
+    ///
+    /// Sends `SIGHUP`, or the `reload_signal` from the per-service config.
+    pub fn reload(&self) -> Result<()> {
+        self.ensure_privileges()?;
+        let signal = match self.config.reload_signal() {
+            Some(name) => parse_signal(name)?,
+            None => SIGHUP,
+        };
+        self.send_signal(signal)?;
         println!(
-            "{:<20} {}",
+            "Service '{}' reloaded with {}.",
             self.service.formula(),
-            self.state.read_state()?.to_string()
+            service_process::signal_name(signal)
         );
         Ok(())
     }
 
-    pub fn states() -> Result<()> {
-        let print_section = |title: &str, formulas: Vec<_>| {
-            println!("{:-<30}", "");
-            println!("{title}");
-            println!("{:-<30}", "");
-            formulas
-                .into_iter()
-                .filter_map(|f| ServiceManager::new(f).ok())
-                .for_each(|s| {
-                    if let Err(e) = s.print_state() {
-                        eprintln!("Error printing state: {:?}", e);
-                    }
-                });
-        };
-
-        print_section("Homebrew service", ServiceHomebrew::formulas()?);
-        print_section("Service user agent", ServiceUserAgent::formulas()?);
+    /// Sends an arbitrary signal to the tracked process group.
+    ///
+    /// If the processes are gone shortly afterwards, the service is marked stopped.
+    pub fn kill(&self, signal: &str) -> Result<()> {
+        self.ensure_privileges()?;
+        let signal = parse_signal(signal)?;
+        let targets = self.send_signal(signal)?;
+        println!(
+            "Sent {} to service '{}'.",
+            service_process::signal_name(signal),
+            self.service.formula()
+        );
 
+        if service_process::wait_for_exit(&targets, SIGNAL_SETTLE_DELAY) {
+            self.state.mark_killed(signal)?;
+            println!("Service '{}' exited.", self.service.formula());
+        }
         Ok(())
     }
 
-    pub fn state(&self) -> Result<()> {
-        let state_data = self.state.read_state()?;
-        if *state_data.status() == ServiceStatus::Running {
-            println!("Service '{}' is running.", self.service.formula());
-        } else {
-            println!("Service '{}' is not running.", self.service.formula());
+    /// Sends `signal` to a running service and returns the targets it reached.
+    fn send_signal(&self, signal: c_int) -> Result<Vec<SignalTarget>> {
+        let state = self.state.read_state()?;
+        let targets = state.signal_targets();
+        if state.status() != &ServiceStatus::Running || targets.is_empty() {
+            return Err(Error::ServiceNotRunning {
+                formula: self.service.formula().to_string(),
+            });
+        }
+
+        service_process::signal_all(&targets, signal).map_err(|(pid, err)| {
+            Error::ServiceSignalFailed {
+                formula: self.service.formula().to_string(),
+                pid,
+                signal: service_process::signal_name(signal),
+                reason: err.to_string(),
+            }
+        })?;
+        Ok(targets)
+    }
+
+    /// Checks the recorded state against the live processes, see `ServiceState::reconcile`.
+    ///
+    /// With `adopt`, a service that is not running takes over processes of its program.
+    pub fn reconcile(&self, adopt: bool) -> Result<()> {
+        let was_running = self.state.read_state()?.status() == &ServiceStatus::Running;
+        let state = self.state.reconcile(adopt)?;
+        if adopt && !was_running && state.status() == &ServiceStatus::Running {
+            println!(
+                "Service '{}' adopted PIDs {:?}.",
+                self.service.formula(),
+                state.pids()
+            );
         }
         Ok(())
     }
 
-    // used in tests
-    #[allow(dead_code)]
-    pub fn service(&self) -> &ServiceType {
+    /// Gathers the status of this service without printing anything.
+    ///
+    /// The recorded state is reconciled with the live processes first.
+    pub fn report(&self) -> Result<StatusReport> {
+        let state = self.state.reconcile(false)?;
+        Ok(StatusReport {
+            formula: self.service.formula().to_string(),
+            label: self.service.label().to_string(),
+            source: self.service.source().to_string(),
+            plist_path: self.service.formula_plist_path().to_string(),
+            status: state.status().clone(),
+            pids: state.pids().to_vec(),
+            uptime_secs: state.uptime().map(|uptime| uptime.as_secs()),
+            stdout_path: self.log.stdout_path().to_string(),
+            stderr_path: self.log.stderr_path().to_string(),
+            last_exit_code: state.last_exit_code(),
+            requires_root: self.service.requires_root(),
+            started_at: state.started_at(),
+            started_by: state.started_by(),
+            restarts: state.restarts(),
+            last_exit_signal: state.last_exit_signal(),
+            last_exit_secs_ago: state.since_last_exit().map(|since| since.as_secs()),
+            health: match state.status() {
+                ServiceStatus::Running => self.health(),
+                _ => None,
+            },
+            history: Vec::new(),
+        })
+    }
+
+    /// Like `report`, with the recorded lifecycle history included.
+    pub fn info(&self) -> Result<StatusReport> {
+        let history: Vec<HistoryEntry> = self.state.read_state()?.history().to_vec();
+        Ok(StatusReport {
+            history,
+            ..self.report()?
+        })
+    }
+
+    /// Gathers the status of every discoverable service, in source precedence order,
+    /// or only of the services in `source` if given.
+    ///
+    /// Services whose state cannot be read are reported on stderr and skipped.
+    pub fn reports(source: Option<&str>, global: &GlobalConfig) -> Result<Vec<StatusReport>> {
+        let reports = ServiceSources::from_env(global)?
+            .discover(source)?
+            .into_iter()
+            .filter_map(|service| ServiceManager::from_resolved(service, global).ok())
+            .filter_map(|s| match s.report() {
+                Ok(report) => Some(report),
+                Err(e) => {
+                    eprintln!("Error reading state of '{}': {:?}", s.formula(), e);
+                    None
+                }
+            })
+            .collect();
+        Ok(reports)
+    }
+
+    pub fn service(&self) -> &ResolvedService {
         &self.service
     }
+
+    pub fn formula(&self) -> &str {
+        self.service.formula()
+    }
+
+    pub(crate) fn global(&self) -> &GlobalConfig {
+        &self.global
+    }
+
+    /// The effective configuration, and where each value came from.
+    pub fn settings(&self) -> &ServiceSettings {
+        &self.settings
+    }
+
+    pub(crate) fn service_state(&self) -> &ServiceState {
+        &self.state
+    }
+}
+
+/// Reads the job of `service` from its plist, native definition or project file,
+/// with the settings of a definition that plists have no key for.
+fn load_definition(service: &ResolvedService) -> Result<(LaunchdConfig, Overrides)> {
+    let formula = service.formula();
+    let path = Path::new(service.formula_plist_path());
+    let native = if service.source() == ProjectSource::NAME {
+        let project = Project::load(path)?;
+        project
+            .definition(formula)
+            .cloned()
+            .ok_or_else(|| Error::FormulaNotFound {
+                formula: formula.to_string(),
+            })?
+    } else if NativeDefinition::is_definition(path) {
+        NativeDefinition::load(path)?
+    } else {
+        let data = std::fs::read(path)?;
+        return Ok((
+            LaunchdConfig::from_bytes(data.as_slice())?,
+            Overrides::default(),
+        ));
+    };
+    Ok((native.launchd_config(formula)?, native.overrides()))
+}
+
+/// Layers the definition, the global `[services]`, the per-service config and `cli`
+/// over the plist, lowest precedence first.
+fn resolve_settings(
+    formula: &str,
+    launchd: &LaunchdConfig,
+    definition: &Overrides,
+    global: &GlobalConfig,
+    config: &ServiceConfig,
+    cli: &Overrides,
+) -> Result<ServiceSettings> {
+    ServiceSettings::resolve(
+        formula,
+        launchd,
+        global,
+        &[
+            (Origin::Plist, definition),
+            (Origin::Global, global.services()),
+            (Origin::Service, config.overrides()),
+            (Origin::Cli, cli),
+        ],
+    )
+}
+
+fn parse_signal(name: &str) -> Result<c_int> {
+    service_process::parse_signal(name).ok_or_else(|| Error::InvalidSignal {
+        signal: name.to_string(),
+    })
 }
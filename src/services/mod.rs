@@ -1,4 +1,10 @@
+pub mod service_config;
+pub mod service_health;
 pub mod service_homebrew;
 pub mod service_log;
+pub mod service_native;
+pub mod service_process;
+pub mod service_settings;
+pub mod service_source;
 pub mod service_state;
 pub mod service_user_agent;
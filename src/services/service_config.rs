@@ -0,0 +1,87 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::error::Result;
+use crate::services::service_log::{LogFormat, LogRotation};
+use crate::services::service_settings::Overrides;
+
+/// Per-service overrides read from `~/.config/flint/<formula>.json`.
+#[derive(Debug, Default, Deserialize, Clone)]
+pub struct ServiceConfig {
+    /// Settings layered over the plist, see `ServiceSettings`.
+    #[serde(flatten)]
+    overrides: Overrides,
+
+    /// Start the service with an empty environment instead of inheriting flint's.
+    #[serde(default)]
+    clear_environment: bool,
+
+    /// Signal sent by `flint reload`, `SIGHUP` when unset.
+    #[serde(default)]
+    reload_signal: Option<String>,
+
+    /// When and how the stdout/stderr logs are rotated.
+    #[serde(default)]
+    log_rotation: LogRotation,
+
+    /// How output captured by flint is written to the logs.
+    #[serde(default)]
+    log_format: LogFormat,
+}
+
+impl ServiceConfig {
+    /// Returns the path of the override file for the given formula.
+    pub fn config_path(formula: &str) -> Result<PathBuf> {
+        let home = std::env::var("HOME")?;
+        Ok(Path::new(&home)
+            .join(".config")
+            .join("flint")
+            .join(format!("{}.json", formula)))
+    }
+
+    /// Loads the override file, or an empty config if there is none.
+    pub fn load(formula: &str) -> Result<Self> {
+        let config_path = Self::config_path(formula)?;
+        if !config_path.exists() {
+            return Ok(ServiceConfig::default());
+        }
+
+        let config_data = fs::read_to_string(&config_path)?;
+        Ok(serde_json::from_str(&config_data)?)
+    }
+
+    pub fn overrides(&self) -> &Overrides {
+        &self.overrides
+    }
+
+    pub fn standard_out_path(&self) -> Option<&str> {
+        self.overrides.standard_out_path.as_deref()
+    }
+
+    pub fn standard_error_path(&self) -> Option<&str> {
+        self.overrides.standard_error_path.as_deref()
+    }
+
+    pub fn environment_variables(&self) -> &BTreeMap<String, String> {
+        &self.overrides.environment_variables
+    }
+
+    pub fn clear_environment(&self) -> bool {
+        self.clear_environment
+    }
+
+    pub fn reload_signal(&self) -> Option<&str> {
+        self.reload_signal.as_deref()
+    }
+
+    pub fn log_rotation(&self) -> &LogRotation {
+        &self.log_rotation
+    }
+
+    pub fn log_format(&self) -> LogFormat {
+        self.log_format
+    }
+}
@@ -0,0 +1,248 @@
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::os::unix::net::UnixStream;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+/// Seconds between two liveness probes under `supervise`.
+pub const DEFAULT_INTERVAL: u64 = 10;
+
+/// Seconds a single probe may take before it counts as failed.
+pub const DEFAULT_TIMEOUT: u64 = 2;
+
+/// Failed probes in a row after which `supervise` restarts the service.
+pub const DEFAULT_RETRIES: u32 = 3;
+
+/// Seconds `start --wait` waits for the first successful probe.
+pub const DEFAULT_START_TIMEOUT: u64 = 30;
+
+/// How often a probe command is checked for completion.
+const COMMAND_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Outcome of the health check of a running service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Health {
+    Healthy,
+    Unhealthy,
+}
+
+/// How to tell whether a service works, beyond its process being alive.
+///
+/// Exactly one of `tcp`, `http`, `unix` and `command` is set.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HealthCheck {
+    /// `host:port` that accepts TCP connections.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tcp: Option<String>,
+
+    /// `http://host[:port]/path` answering a GET with a 2xx or 3xx status.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http: Option<String>,
+
+    /// Path of a Unix socket that accepts connections.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unix: Option<String>,
+
+    /// Shell command that exits with 0.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+
+    /// Seconds between two probes under `supervise`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval: Option<u64>,
+
+    /// Seconds a probe may take.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+
+    /// Failed probes in a row before `supervise` restarts the service.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
+
+    /// Seconds `start --wait` waits for the service to become healthy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_timeout: Option<u64>,
+}
+
+/// The one probe a `HealthCheck` runs.
+enum Probe<'a> {
+    Tcp(&'a str),
+    Http(&'a str),
+    Unix(&'a str),
+    Command(&'a str),
+}
+
+impl HealthCheck {
+    /// Fails unless exactly one probe is set and the timings are usable.
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |reason: &str| {
+            Err(Error::InvalidHealthCheck {
+                reason: reason.to_string(),
+            })
+        };
+        let probes = [&self.tcp, &self.http, &self.unix, &self.command];
+        if probes.iter().filter(|probe| probe.is_some()).count() != 1 {
+            return invalid("set exactly one of tcp, http, unix or command");
+        }
+        if let Some(url) = &self.http
+            && !url.starts_with("http://")
+        {
+            return invalid("http must be an http:// URL");
+        }
+        if self.interval == Some(0) || self.timeout == Some(0) || self.start_timeout == Some(0) {
+            return invalid("interval, timeout and start_timeout must be at least 1 second");
+        }
+        if self.retries == Some(0) {
+            return invalid("retries must be at least 1");
+        }
+        Ok(())
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval.unwrap_or(DEFAULT_INTERVAL))
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout.unwrap_or(DEFAULT_TIMEOUT))
+    }
+
+    pub fn retries(&self) -> u32 {
+        self.retries.unwrap_or(DEFAULT_RETRIES)
+    }
+
+    pub fn start_timeout(&self) -> Duration {
+        Duration::from_secs(self.start_timeout.unwrap_or(DEFAULT_START_TIMEOUT))
+    }
+
+    fn probe(&self) -> Option<Probe<'_>> {
+        match (&self.tcp, &self.http, &self.unix, &self.command) {
+            (Some(address), ..) => Some(Probe::Tcp(address)),
+            (_, Some(url), ..) => Some(Probe::Http(url)),
+            (_, _, Some(path), _) => Some(Probe::Unix(path)),
+            (_, _, _, Some(command)) => Some(Probe::Command(command)),
+            _ => None,
+        }
+    }
+
+    /// Runs the probe once, returning why it failed.
+    pub fn check(&self) -> std::result::Result<(), String> {
+        let timeout = self.timeout();
+        let Some(probe) = self.probe() else {
+            return Err("no probe is set".to_string());
+        };
+        match probe {
+            Probe::Tcp(address) => connect_tcp(address, timeout).map(drop),
+            Probe::Http(url) => check_http(url, timeout),
+            Probe::Unix(path) => UnixStream::connect(path)
+                .map(drop)
+                .map_err(|err| format!("cannot connect to {}: {}", path, err)),
+            Probe::Command(command) => run_command(command, timeout),
+        }
+    }
+
+    /// Short description, e.g. `tcp 127.0.0.1:5432`.
+    pub fn describe(&self) -> String {
+        match self.probe() {
+            Some(Probe::Tcp(address)) => format!("tcp {}", address),
+            Some(Probe::Http(url)) => format!("http {}", url),
+            Some(Probe::Unix(path)) => format!("unix {}", path),
+            Some(Probe::Command(command)) => format!("command {}", command),
+            None => "-".to_string(),
+        }
+    }
+}
+
+fn connect_tcp(address: &str, timeout: Duration) -> std::result::Result<TcpStream, String> {
+    let addresses = address
+        .to_socket_addrs()
+        .map_err(|err| format!("cannot resolve {}: {}", address, err))?;
+    let mut last_error = format!("{} resolves to no address", address);
+    for socket_address in addresses {
+        match TcpStream::connect_timeout(&socket_address, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_error = format!("cannot connect to {}: {}", address, err),
+        }
+    }
+    Err(last_error)
+}
+
+/// Sends a plain HTTP/1.0 GET and accepts any 2xx or 3xx status.
+fn check_http(url: &str, timeout: Duration) -> std::result::Result<(), String> {
+    let rest = url.strip_prefix("http://").unwrap_or(url);
+    let (authority, path) = match rest.find('/') {
+        Some(slash) => rest.split_at(slash),
+        None => (rest, "/"),
+    };
+    let address = if authority.contains(':') {
+        authority.to_string()
+    } else {
+        format!("{}:80", authority)
+    };
+
+    let mut stream = connect_tcp(&address, timeout)?;
+    let io_error = |err: std::io::Error| format!("{}: {}", url, err);
+    stream.set_read_timeout(Some(timeout)).map_err(io_error)?;
+    stream.set_write_timeout(Some(timeout)).map_err(io_error)?;
+    write!(
+        stream,
+        "GET {} HTTP/1.0\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        path, authority
+    )
+    .map_err(io_error)?;
+
+    let mut head = Vec::new();
+    let mut buffer = [0u8; 512];
+    while !head.contains(&b'\n') && head.len() < 4096 {
+        match stream.read(&mut buffer).map_err(io_error)? {
+            0 => break,
+            read => head.extend_from_slice(&buffer[..read]),
+        }
+    }
+    let head = String::from_utf8_lossy(&head);
+    let status: u16 = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| format!("{}: no HTTP status in the response", url))?;
+    if (200..400).contains(&status) {
+        Ok(())
+    } else {
+        Err(format!("{} answered {}", url, status))
+    }
+}
+
+/// Runs `command` through `sh`, killing it once `timeout` elapses.
+fn run_command(command: &str, timeout: Duration) -> std::result::Result<(), String> {
+    let mut child = Command::new("/bin/sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|err| format!("cannot run `{}`: {}", command, err))?;
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return Ok(()),
+            Ok(Some(status)) => return Err(format!("`{}` exited with {}", command, status)),
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!(
+                    "`{}` timed out after {}s",
+                    command,
+                    timeout.as_secs()
+                ));
+            }
+            Ok(None) => thread::sleep(COMMAND_POLL_INTERVAL),
+            Err(err) => return Err(format!("`{}`: {}", command, err)),
+        }
+    }
+}
@@ -1,7 +1,12 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
 
 use crate::error::{Error, Result};
 
+/// Default Homebrew prefixes: Apple Silicon, Intel macOS and Linuxbrew.
+const KNOWN_PREFIXES: &[&str] = &["/opt/homebrew", "/usr/local", "/home/linuxbrew/.linuxbrew"];
+
 #[derive(Debug)]
 pub struct ServiceHomebrew {
     formula: String,
@@ -9,9 +14,40 @@ pub struct ServiceHomebrew {
 }
 
 impl ServiceHomebrew {
-    pub fn new(formula: String) -> Result<Self> {
-        let formula_path = format!("/opt/homebrew/opt/{}", formula);
-        let formula_path = Path::new(&formula_path);
+    /// Resolves the Homebrew prefix to use.
+    ///
+    /// In order: `$HOMEBREW_PREFIX`, the `configured` prefix, `brew --prefix`,
+    /// then the first known location that has an `opt` directory.
+    pub fn resolve_prefix(configured: Option<&Path>) -> Option<PathBuf> {
+        if let Some(prefix) = std::env::var_os("HOMEBREW_PREFIX").filter(|p| !p.is_empty()) {
+            return Some(PathBuf::from(prefix));
+        }
+        if let Some(prefix) = configured {
+            return Some(prefix.to_path_buf());
+        }
+        if let Some(prefix) = Self::brew_prefix() {
+            return Some(prefix.clone());
+        }
+        KNOWN_PREFIXES
+            .iter()
+            .map(PathBuf::from)
+            .find(|prefix| prefix.join("opt").is_dir())
+    }
+
+    /// Output of `brew --prefix`, computed once per process since brew is slow to start.
+    fn brew_prefix() -> Option<&'static PathBuf> {
+        static BREW_PREFIX: OnceLock<Option<PathBuf>> = OnceLock::new();
+        BREW_PREFIX
+            .get_or_init(|| {
+                let output = Command::new("brew").arg("--prefix").output().ok()?;
+                let prefix = String::from_utf8_lossy(&output.stdout).trim().to_string();
+                (output.status.success() && !prefix.is_empty()).then(|| PathBuf::from(prefix))
+            })
+            .as_ref()
+    }
+
+    pub fn new(formula: String, prefix: &Path) -> Result<Self> {
+        let formula_path = prefix.join("opt").join(&formula);
         if !formula_path.exists() {
             return Err(Error::FormulaNotFound { formula });
         }
@@ -26,8 +62,8 @@ impl ServiceHomebrew {
         })
     }
 
-    pub fn formulas() -> Result<Vec<String>> {
-        let formulas: Vec<_> = std::fs::read_dir("/opt/homebrew/opt/")?
+    pub fn formulas(prefix: &Path) -> Result<Vec<String>> {
+        let formulas: Vec<_> = std::fs::read_dir(prefix.join("opt"))?
             .filter_map(|e| e.ok())
             .filter(|e| {
                 e.path()
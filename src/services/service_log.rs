@@ -1,76 +1,207 @@
-use std::{fs, path::Path};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use crate::{error::Result, launchd_config::LaunchdConfig};
+use serde::{Deserialize, Serialize};
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
 
-pub struct ServiceLog {
-    formula: String,
-    stdout_path: String,
-    stderr_path: String,
+use crate::{
+    error::Result,
+    global_config::GlobalConfig,
+    launchd_config::LaunchdConfig,
+    services::{
+        service_config::ServiceConfig,
+        service_settings::{Origin, ServiceSettings},
+    },
+};
+
+const SECS_PER_DAY: u64 = 86_400;
+
+/// Longest line held back waiting for its newline before it is written anyway.
+const MAX_PENDING_LINE: usize = 64 * 1024;
+
+/// Rotation policy of a service's logs, the `log_rotation` key of its config.
+///
+/// Rotated files are named `<log>.1` (newest) to `<log>.<max_files>`, with a
+/// `.gz` suffix when compressed. Without `max_size` or `daily` nothing rotates.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct LogRotation {
+    /// Rotate once a log would grow past this many bytes.
+    pub max_size: Option<u64>,
+    /// How many rotated files to keep per log.
+    pub max_files: usize,
+    /// Rotate a log last written on an earlier day (UTC).
+    pub daily: bool,
+    /// Compress rotated files with gzip.
+    pub compress: bool,
 }
 
-impl ServiceLog {
-    /// Create and initialize a new `ServiceLog` instance.
-    pub fn new(formula: String, launchd_service: &LaunchdConfig) -> Result<Self> {
-        let mut service_log = ServiceLog {
-            formula,
-            stdout_path: String::new(),
-            stderr_path: String::new(),
-        };
+impl Default for LogRotation {
+    fn default() -> Self {
+        LogRotation {
+            max_size: None,
+            max_files: 5,
+            daily: false,
+            compress: false,
+        }
+    }
+}
 
-        service_log
-            .resolve_paths((launchd_service.stdout_path(), launchd_service.stderr_path()))?;
-        Ok(service_log)
-    }
-
-    /// Initialize log paths based on user config, plist, or default fallback.
-    fn resolve_paths(&mut self, launchd_output_path: (Option<&str>, Option<&str>)) -> Result<()> {
-        let home = std::env::var("HOME")?;
-        let config_path = Path::new(&home)
-            .join(".config")
-            .join("flint")
-            .join(format!("{}.json", self.formula));
-
-        // 1️⃣ Prefer custom JSON config if available
-        if config_path.exists() {
-            #[derive(serde::Deserialize)]
-            struct LogConfig {
-                standard_out_path: Option<String>,
-                standard_error_path: Option<String>,
-            }
+impl LogRotation {
+    /// Whether a log of `size` bytes last modified on `day` must rotate before
+    /// `incoming` more bytes are written on `today`.
+    fn is_due(&self, size: u64, incoming: u64, day: u64, today: u64) -> bool {
+        let too_big = self
+            .max_size
+            .is_some_and(|max_size| size > 0 && size + incoming > max_size);
+        let new_day = self.daily && size > 0 && day < today;
+        too_big || new_day
+    }
+}
 
-            let config_data = fs::read_to_string(&config_path)?;
-            let config: LogConfig = serde_json::from_str(&config_data)?;
+/// How captured output lines are written, the `log_format` key of a service's config.
+///
+/// Only output piped through flint, as under `flint supervise`, can be formatted;
+/// a service started on its own writes to its logs directly.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// Lines as the service wrote them.
+    #[default]
+    Raw,
+    /// `<RFC 3339 timestamp> <stream>[<pid>] <line>`.
+    Prefixed,
+    /// One `{"ts":…,"stream":…,"pid":…,"line":…}` object per line.
+    Json,
+}
 
-            if let Some(stdout) = config.standard_out_path {
-                self.stdout_path = stdout;
-            }
-            if let Some(stderr) = config.standard_error_path {
-                self.stderr_path = stderr;
-            }
+/// Formats the output of one stream of a running service, line by line.
+pub struct LineFormatter {
+    format: LogFormat,
+    stream: &'static str,
+    pid: u32,
+    pending: Vec<u8>,
+}
+
+impl LineFormatter {
+    /// `stream` is the name written with each line, `stdout` or `stderr`.
+    pub fn new(format: LogFormat, stream: &'static str, pid: u32) -> Self {
+        LineFormatter {
+            format,
+            stream,
+            pid,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Formats the complete lines of `chunk`, keeping a trailing partial line
+    /// for the next call.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<u8> {
+        if self.format == LogFormat::Raw {
+            return chunk.to_vec();
+        }
+        self.pending.extend_from_slice(chunk);
+
+        let mut formatted = Vec::new();
+        let complete = match self.pending.iter().rposition(|&byte| byte == b'\n') {
+            Some(last_newline) => last_newline + 1,
+            None if self.pending.len() >= MAX_PENDING_LINE => self.pending.len(),
+            None => return formatted,
+        };
+        let lines: Vec<u8> = self.pending.drain(..complete).collect();
+        for line in lines.split_inclusive(|&byte| byte == b'\n') {
+            self.format_line(line, &mut formatted);
+        }
+        formatted
+    }
 
-            return Ok(());
+    /// Formats the partial line left once the stream has closed.
+    pub fn finish(&mut self) -> Vec<u8> {
+        let mut formatted = Vec::new();
+        if !self.pending.is_empty() {
+            let line = std::mem::take(&mut self.pending);
+            self.format_line(&line, &mut formatted);
         }
+        formatted
+    }
 
-        // 2️⃣ Use plist-provided paths if present
-        let stdout_plist = launchd_output_path.0;
-        let stderr_plist = launchd_output_path.1;
+    fn format_line(&self, line: &[u8], out: &mut Vec<u8>) {
+        let line = String::from_utf8_lossy(line);
+        let line = line.strip_suffix('\n').unwrap_or(&line);
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        let ts = OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .unwrap_or_default();
 
-        if stdout_plist.is_some() || stderr_plist.is_some() {
-            if let Some(stdout) = stdout_plist {
-                self.stdout_path = stdout.to_string();
+        match self.format {
+            LogFormat::Raw => out.extend_from_slice(line.as_bytes()),
+            LogFormat::Prefixed => {
+                out.extend_from_slice(
+                    format!("{} {}[{}] {}", ts, self.stream, self.pid, line).as_bytes(),
+                );
             }
-            if let Some(stderr) = stderr_plist {
-                self.stderr_path = stderr.to_string();
+            LogFormat::Json => {
+                let record = JsonLine {
+                    ts: &ts,
+                    stream: self.stream,
+                    pid: self.pid,
+                    line,
+                };
+                if let Ok(json) = serde_json::to_vec(&record) {
+                    out.extend_from_slice(&json);
+                }
             }
-
-            return Ok(());
         }
+        out.push(b'\n');
+    }
+}
 
-        // 3️⃣ Fallback to defaults
+/// A captured line in the `json` log format.
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    ts: &'a str,
+    stream: &'a str,
+    pid: u32,
+    line: &'a str,
+}
 
-        self.stdout_path = format!("{}/Library/Logs/Flint/{}.log", home, self.formula);
-        self.stderr_path = format!("{}/Library/Logs/Flint/{}_error.log", home, self.formula);
-        Ok(())
+pub struct ServiceLog {
+    stdout_path: String,
+    stderr_path: String,
+    rotation: LogRotation,
+    format: LogFormat,
+}
+
+impl ServiceLog {
+    /// Logs of a service as set by its plist and per-service config, falling back
+    /// to `~/Library/Logs/Flint/<formula>.log` and `<formula>_error.log`.
+    pub fn new(
+        formula: String,
+        launchd_service: &LaunchdConfig,
+        config: &ServiceConfig,
+    ) -> Result<Self> {
+        let settings = ServiceSettings::resolve(
+            &formula,
+            launchd_service,
+            &GlobalConfig::default(),
+            &[(Origin::Service, config.overrides())],
+        )?;
+        Ok(Self::from_settings(&settings, config))
+    }
+
+    /// Logs at the effective paths of `settings`, rotated and formatted per `config`.
+    pub fn from_settings(settings: &ServiceSettings, config: &ServiceConfig) -> Self {
+        ServiceLog {
+            stdout_path: settings.stdout_path.value.clone(),
+            stderr_path: settings.stderr_path.value.clone(),
+            rotation: config.log_rotation().clone(),
+            format: config.log_format(),
+        }
     }
 
     pub fn create_log_dirs(&self) -> Result<()> {
@@ -83,6 +214,34 @@ impl ServiceLog {
         Ok(())
     }
 
+    /// Opens the stdout log for appending, rotating it first if it is due.
+    pub fn open_stdout(&self) -> Result<File> {
+        open_for_append(Path::new(&self.stdout_path), &self.rotation)
+    }
+
+    /// Opens the stderr log for appending, rotating it first if it is due.
+    pub fn open_stderr(&self) -> Result<File> {
+        open_for_append(Path::new(&self.stderr_path), &self.rotation)
+    }
+
+    /// A writer for the stdout log that keeps rotating it while flint pipes output.
+    pub fn stdout_writer(&self) -> Result<LogWriter> {
+        LogWriter::open(PathBuf::from(&self.stdout_path), self.rotation.clone())
+    }
+
+    /// A writer for the stderr log that keeps rotating it while flint pipes output.
+    pub fn stderr_writer(&self) -> Result<LogWriter> {
+        LogWriter::open(PathBuf::from(&self.stderr_path), self.rotation.clone())
+    }
+
+    pub fn rotation(&self) -> &LogRotation {
+        &self.rotation
+    }
+
+    pub fn format(&self) -> LogFormat {
+        self.format
+    }
+
     /// Return current stdout path.
     pub fn stdout_path(&self) -> &str {
         &self.stdout_path
@@ -93,3 +252,132 @@ impl ServiceLog {
         &self.stderr_path
     }
 }
+
+/// Appending writer for a log file that rotates it according to a `LogRotation`.
+///
+/// A file descriptor handed to a child cannot be rotated under it, so this is
+/// used when flint itself pipes the child's output into the log.
+pub struct LogWriter {
+    path: PathBuf,
+    rotation: LogRotation,
+    file: File,
+    size: u64,
+    day: u64,
+}
+
+impl LogWriter {
+    pub fn open(path: PathBuf, rotation: LogRotation) -> Result<Self> {
+        let file = open_for_append(&path, &rotation)?;
+        let (size, day) = size_and_day(&path);
+        Ok(LogWriter {
+            path,
+            rotation,
+            file,
+            size,
+            day,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let today = today();
+        if self
+            .rotation
+            .is_due(self.size, buf.len() as u64, self.day, today)
+        {
+            self.file.flush()?;
+            rotate(&self.path, &self.rotation).map_err(io::Error::other)?;
+            self.file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+            self.size = 0;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        self.day = today;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Opens `path` for appending, creating its directory and rotating it first if due.
+fn open_for_append(path: &Path, rotation: &LogRotation) -> Result<File> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let (size, day) = size_and_day(path);
+    if rotation.is_due(size, 0, day, today()) {
+        rotate(path, rotation)?;
+    }
+    Ok(OpenOptions::new().create(true).append(true).open(path)?)
+}
+
+/// Shifts `path` to `path.1`, `path.1` to `path.2` and so on, dropping files past
+/// `max_files`, then compresses the newly rotated file if asked to.
+pub fn rotate(path: &Path, rotation: &LogRotation) -> Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    if rotation.max_files == 0 {
+        fs::remove_file(path)?;
+        return Ok(());
+    }
+
+    for index in (1..=rotation.max_files).rev() {
+        for suffix in ["", ".gz"] {
+            let from = rotated_path(path, index, suffix);
+            if !from.exists() {
+                continue;
+            }
+            if index == rotation.max_files {
+                fs::remove_file(&from)?;
+            } else {
+                fs::rename(&from, rotated_path(path, index + 1, suffix))?;
+            }
+        }
+    }
+
+    let newest = rotated_path(path, 1, "");
+    fs::rename(path, &newest)?;
+    if rotation.compress {
+        Command::new("gzip").arg("-f").arg(&newest).status()?;
+    }
+    Ok(())
+}
+
+/// `<path>.<index><suffix>`, e.g. `redis.log.2.gz`.
+pub fn rotated_path(path: &Path, index: usize, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{}{}", index, suffix));
+    PathBuf::from(name)
+}
+
+/// Size of a log and the day it was last modified, or zeros if it does not exist.
+fn size_and_day(path: &Path) -> (u64, u64) {
+    let Ok(metadata) = fs::metadata(path) else {
+        return (0, 0);
+    };
+    let day = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|since_epoch| since_epoch.as_secs() / SECS_PER_DAY)
+        .unwrap_or_default();
+    (metadata.len(), day)
+}
+
+fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() / SECS_PER_DAY)
+        .unwrap_or_default()
+}
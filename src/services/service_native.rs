@@ -0,0 +1,179 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::error::Result;
+use crate::global_config::{config_error, expand_home};
+use crate::launchd_config::{KeepAlive, LaunchdConfig, LaunchdConfigBuilder};
+use crate::services::service_health::HealthCheck;
+use crate::services::service_settings::Overrides;
+
+/// Label given to native services whose definition does not set one.
+const LABEL_PREFIX: &str = "flint.";
+
+/// A service defined for flint itself, in `~/.config/flint/services/<name>.toml`.
+///
+/// It describes the same job as a launchd plist, with flint's key names, and
+/// needs no launchd to run.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NativeDefinition {
+    /// launchd-style label, `flint.<name>` when unset.
+    #[serde(default)]
+    label: Option<String>,
+
+    /// Path of the executable, or a name looked up in `$PATH`.
+    program: String,
+
+    #[serde(default)]
+    args: Vec<String>,
+
+    #[serde(default)]
+    environment_variables: BTreeMap<String, String>,
+
+    #[serde(default)]
+    working_directory: Option<String>,
+
+    #[serde(default)]
+    standard_out_path: Option<String>,
+
+    #[serde(default)]
+    standard_error_path: Option<String>,
+
+    /// Restart policy, a boolean or the `KeepAlive` conditions.
+    #[serde(default)]
+    keep_alive: Option<KeepAlive>,
+
+    #[serde(default)]
+    throttle_interval: Option<u64>,
+
+    #[serde(default)]
+    exit_timeout: Option<u64>,
+
+    /// Services that must be running for this one to start.
+    #[serde(default)]
+    requires: Vec<String>,
+
+    /// Services started before this one when started together.
+    #[serde(default)]
+    after: Vec<String>,
+
+    /// Probe telling whether the service works, as a `[health_check]` table.
+    #[serde(default)]
+    health_check: Option<HealthCheck>,
+}
+
+impl NativeDefinition {
+    /// Directory of the native definitions, `~/.config/flint/services`.
+    pub fn services_dir() -> Result<PathBuf> {
+        let home = std::env::var("HOME")?;
+        Ok(Path::new(&home)
+            .join(".config")
+            .join("flint")
+            .join("services"))
+    }
+
+    /// Whether `path` is a native definition rather than a plist.
+    pub fn is_definition(path: &Path) -> bool {
+        path.extension()
+            .is_some_and(|extension| extension == "toml")
+    }
+
+    /// Names of the definitions in `dir`, sorted, with their paths.
+    pub fn definitions_in(dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+        let mut definitions = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if !Self::is_definition(&path) {
+                continue;
+            }
+            if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                definitions.push((name.to_string(), path.clone()));
+            }
+        }
+        definitions.sort();
+        Ok(definitions)
+    }
+
+    /// Reads a definition; syntax errors point to the offending key and line.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)?;
+        toml::from_str(&text)
+            .map_err(|err| config_error(path, &text, err.span().unwrap_or(0..0), err.message()))
+    }
+
+    /// A service running `command` through `sh`, as a Procfile line does.
+    pub(crate) fn shell(command: &str) -> Self {
+        NativeDefinition {
+            program: "/bin/sh".to_string(),
+            args: vec!["-c".to_string(), command.to_string()],
+            ..NativeDefinition::default()
+        }
+    }
+
+    /// Fills in the label and working directory when the definition leaves them out.
+    pub(crate) fn or_defaults(mut self, label: String, working_directory: &Path) -> Self {
+        self.label.get_or_insert(label);
+        self.working_directory
+            .get_or_insert_with(|| working_directory.display().to_string());
+        self
+    }
+
+    /// The service's label, `flint.<name>` unless the definition sets one.
+    pub fn label(&self, name: &str) -> String {
+        self.label
+            .clone()
+            .unwrap_or_else(|| Self::default_label(name))
+    }
+
+    pub fn default_label(name: &str) -> String {
+        format!("{}{}", LABEL_PREFIX, name)
+    }
+
+    /// The job as a `LaunchdConfig`, with `~` expanded and the program found in `$PATH`.
+    pub fn launchd_config(&self, name: &str) -> Result<LaunchdConfig> {
+        let expand = |path: &str| expand_home(Path::new(path)).display().to_string();
+        LaunchdConfigBuilder::default()
+            .label(Some(self.label(name)))
+            .program(Some(find_program(&self.program)))
+            .args(self.args.clone())
+            .environment_variables(self.environment_variables.clone())
+            .working_directory(
+                self.working_directory
+                    .as_deref()
+                    .map_or_else(|| ".".to_string(), expand),
+            )
+            .stdout_path(self.standard_out_path.as_deref().map(expand))
+            .stderr_path(self.standard_error_path.as_deref().map(expand))
+            .keep_alive(self.keep_alive.clone())
+            .throttle_interval(self.throttle_interval)
+            .exit_timeout(self.exit_timeout)
+            .build()
+    }
+
+    /// Settings of the definition that plists have no key for.
+    pub fn overrides(&self) -> Overrides {
+        Overrides {
+            requires: Some(self.requires.clone()).filter(|names| !names.is_empty()),
+            after: Some(self.after.clone()).filter(|names| !names.is_empty()),
+            health_check: self.health_check.clone(),
+            ..Overrides::default()
+        }
+    }
+}
+
+/// `program` itself if it is a path, else its first match in `$PATH`.
+fn find_program(program: &str) -> String {
+    if program.contains('/') {
+        return expand_home(Path::new(program)).display().to_string();
+    }
+    std::env::var_os("PATH")
+        .and_then(|paths| {
+            std::env::split_paths(&paths)
+                .map(|dir| dir.join(program))
+                .find(|candidate| candidate.is_file())
+        })
+        .map_or_else(|| program.to_string(), |path| path.display().to_string())
+}
@@ -0,0 +1,223 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use nix::libc::{EPERM, ESRCH, c_int, kill};
+use nix::sys::signal::Signal;
+
+/// Interval between liveness checks while waiting for processes to exit.
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Something `kill(2)` can be aimed at: a single process or a whole process group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignalTarget {
+    Process(i32),
+    Group(i32),
+}
+
+impl SignalTarget {
+    /// The `kill(2)` argument, or `None` for ids that would hit flint itself or everything.
+    fn raw(&self) -> Option<i32> {
+        match *self {
+            SignalTarget::Process(pid) if pid > 0 => Some(pid),
+            SignalTarget::Group(pgid) if pgid > 1 => Some(-pgid),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the process, or any member of the group, still exists.
+    pub fn is_alive(&self) -> bool {
+        let Some(raw) = self.raw() else {
+            return false;
+        };
+        if unsafe { kill(raw, 0) } == 0 {
+            return true;
+        }
+        // The process exists but belongs to someone else.
+        std::io::Error::last_os_error().raw_os_error() == Some(EPERM)
+    }
+
+    /// Sends `signal`. A target that has already exited is not an error.
+    pub fn signal(&self, signal: c_int) -> std::io::Result<()> {
+        let Some(raw) = self.raw() else {
+            return Ok(());
+        };
+        if unsafe { kill(raw, signal) } == 0 {
+            return Ok(());
+        }
+        let err = std::io::Error::last_os_error();
+        if err.raw_os_error() == Some(ESRCH) {
+            return Ok(());
+        }
+        Err(err)
+    }
+
+    pub fn pid(&self) -> i32 {
+        match *self {
+            SignalTarget::Process(pid) | SignalTarget::Group(pid) => pid,
+        }
+    }
+}
+
+/// Parses a signal given as `HUP`, `SIGHUP` or `1`.
+pub fn parse_signal(name: &str) -> Option<c_int> {
+    let signal = match name.trim().parse::<i32>() {
+        Ok(number) => Signal::try_from(number).ok()?,
+        Err(_) => {
+            let name = name.trim().to_ascii_uppercase();
+            if name.starts_with("SIG") {
+                name.parse::<Signal>().ok()?
+            } else {
+                format!("SIG{}", name).parse::<Signal>().ok()?
+            }
+        }
+    };
+    Some(signal as c_int)
+}
+
+/// Returns the conventional name of a signal number, e.g. `SIGHUP`.
+pub fn signal_name(signal: c_int) -> String {
+    Signal::try_from(signal)
+        .map(|signal| signal.as_str().to_string())
+        .unwrap_or_else(|_| signal.to_string())
+}
+
+/// Returns whether a process with this PID currently exists.
+pub fn is_alive(pid: i32) -> bool {
+    SignalTarget::Process(pid).is_alive()
+}
+
+/// Sends `signal` to every target, returning the PID and error of the first failure.
+pub fn signal_all(targets: &[SignalTarget], signal: c_int) -> Result<(), (i32, std::io::Error)> {
+    for target in targets {
+        target.signal(signal).map_err(|err| (target.pid(), err))?;
+    }
+    Ok(())
+}
+
+/// Blocks until every target is gone, returning `false` if `timeout` elapses first.
+pub fn wait_for_exit(targets: &[SignalTarget], timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if !targets.iter().any(SignalTarget::is_alive) {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        thread::sleep(EXIT_POLL_INTERVAL);
+    }
+}
+
+/// Returns an opaque token describing when `pid` was started, or `None` if it is gone.
+///
+/// Two processes reusing the same PID get different tokens, which is what lets
+/// flint tell its own child apart from a recycled PID.
+pub fn start_time(pid: i32) -> Option<String> {
+    if !is_alive(pid) {
+        return None;
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        // Field 22 of /proc/<pid>/stat is the start time in clock ticks since boot.
+        // The command name (field 2) may contain spaces, so split after its ')'.
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        let fields = &stat[stat.rfind(')')? + 1..];
+        fields.split_whitespace().nth(19).map(str::to_string)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let output = Command::new("ps")
+            .args(["-o", "lstart=", "-p", &pid.to_string()])
+            .output()
+            .ok()?;
+        let lstart = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (output.status.success() && !lstart.is_empty()).then_some(lstart)
+    }
+}
+
+/// Whether `pid` is a live process running `program`.
+///
+/// The executable is compared first, then the first two arguments so scripts run
+/// through an interpreter are recognised. Zombies count as gone. Returns `None`
+/// when the process cannot be inspected, e.g. because it belongs to another user.
+pub fn runs_program(pid: i32, program: &Path) -> Option<bool> {
+    if !is_alive(pid) {
+        return Some(false);
+    }
+    let (state, executable, args) = inspect(pid)?;
+    if state.starts_with('Z') {
+        return Some(false);
+    }
+    if executable.is_none() && args.is_empty() {
+        return None;
+    }
+
+    let canonical = program.canonicalize().ok();
+    let matches = |candidate: &Path| {
+        candidate == program
+            || canonical.as_deref() == Some(candidate)
+            || candidate.file_name().is_some() && candidate.file_name() == program.file_name()
+    };
+    Some(
+        executable.as_deref().is_some_and(matches)
+            || args.iter().take(2).any(|arg| matches(Path::new(arg))),
+    )
+}
+
+/// State letter, executable path and arguments of `pid`, or `None` if it is gone.
+#[cfg(target_os = "linux")]
+fn inspect(pid: i32) -> Option<(String, Option<PathBuf>, Vec<String>)> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    let state = stat[stat.rfind(')')? + 1..]
+        .split_whitespace()
+        .next()?
+        .to_string();
+    let executable = std::fs::read_link(format!("/proc/{}/exe", pid)).ok();
+    let args = std::fs::read(format!("/proc/{}/cmdline", pid))
+        .map(|cmdline| {
+            cmdline
+                .split(|byte| *byte == 0)
+                .filter(|arg| !arg.is_empty())
+                .map(|arg| String::from_utf8_lossy(arg).to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    Some((state, executable, args))
+}
+
+/// State letter, executable path and arguments of `pid`, or `None` if it is gone.
+#[cfg(not(target_os = "linux"))]
+fn inspect(pid: i32) -> Option<(String, Option<PathBuf>, Vec<String>)> {
+    let ps = |field: &str| {
+        let output = Command::new("ps")
+            .args(["-o", field, "-p", &pid.to_string()])
+            .output()
+            .ok()?;
+        let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (output.status.success() && !value.is_empty()).then_some(value)
+    };
+    let state = ps("stat=")?;
+    let executable = ps("comm=").map(PathBuf::from);
+    let args = ps("args=")
+        .map(|args| args.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default();
+    Some((state, executable, args))
+}
+
+/// Lists PIDs whose process name matches `binary_name`, via `pgrep`.
+pub fn find_by_name(binary_name: &str) -> std::io::Result<Vec<i32>> {
+    let output = Command::new("pgrep").arg(binary_name).output()?;
+
+    if !output.status.success() {
+        return Ok(Vec::new());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.trim().parse::<i32>().ok())
+        .collect())
+}
@@ -0,0 +1,254 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+use nix::libc::{SIGTERM, c_int};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::global_config::GlobalConfig;
+use crate::launchd_config::{
+    DEFAULT_EXIT_TIMEOUT, DEFAULT_THROTTLE_INTERVAL, KeepAlive, LaunchdConfig,
+};
+use crate::services::service_health::HealthCheck;
+use crate::services::service_process;
+
+/// Where an effective setting came from, lowest precedence first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Origin {
+    /// flint's built-in default.
+    Default,
+    /// The service's launchd plist or native definition.
+    Plist,
+    /// The global config, `~/.config/flint/config.toml`.
+    Global,
+    /// The per-service config, `~/.config/flint/<formula>.json`.
+    Service,
+    /// Flags given on the command line.
+    Cli,
+}
+
+impl std::fmt::Display for Origin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Origin::Default => "default",
+            Origin::Plist => "plist",
+            Origin::Global => "global config",
+            Origin::Service => "service config",
+            Origin::Cli => "command line",
+        };
+        f.write_str(name)
+    }
+}
+
+/// An effective value and the layer that set it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Setting<T> {
+    pub value: T,
+    pub origin: Origin,
+}
+
+impl<T> Setting<T> {
+    fn new(value: T, origin: Origin) -> Self {
+        Setting { value, origin }
+    }
+
+    fn set(&mut self, value: Option<T>, origin: Origin) {
+        if let Some(value) = value {
+            *self = Setting::new(value, origin);
+        }
+    }
+}
+
+/// Settings one layer can override; anything left unset falls through to the
+/// layers below it.
+#[derive(Debug, Default, Clone, Deserialize, PartialEq)]
+pub struct Overrides {
+    /// Arguments passed to the program, replacing the plist ones.
+    #[serde(default)]
+    pub args: Option<Vec<String>>,
+
+    /// Variables merged over those of the layers below, one by one.
+    #[serde(default)]
+    pub environment_variables: BTreeMap<String, String>,
+
+    #[serde(default)]
+    pub working_directory: Option<String>,
+
+    #[serde(default)]
+    pub standard_out_path: Option<String>,
+
+    #[serde(default)]
+    pub standard_error_path: Option<String>,
+
+    /// Restart policy, with the same meaning as the plist `KeepAlive`.
+    #[serde(default)]
+    pub keep_alive: Option<KeepAlive>,
+
+    /// Minimum seconds between two launches when restarting.
+    #[serde(default)]
+    pub throttle_interval: Option<u64>,
+
+    /// Signal sent first when stopping, `SIGTERM` when unset.
+    #[serde(default)]
+    pub stop_signal: Option<String>,
+
+    /// Seconds to wait after the stop signal before sending SIGKILL.
+    #[serde(default)]
+    pub exit_timeout: Option<u64>,
+
+    /// Services that must be running for this one to start.
+    #[serde(default)]
+    pub requires: Option<Vec<String>>,
+
+    /// Services started before this one when started together.
+    #[serde(default)]
+    pub after: Option<Vec<String>>,
+
+    /// Probe telling whether the service works, replacing the whole check below.
+    #[serde(default)]
+    pub health_check: Option<HealthCheck>,
+}
+
+/// The effective configuration of a service, every value tagged with its origin.
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceSettings {
+    pub program: Setting<String>,
+    pub args: Setting<Vec<String>>,
+    pub environment_variables: BTreeMap<String, Setting<String>>,
+    pub working_directory: Setting<String>,
+    pub stdout_path: Setting<String>,
+    pub stderr_path: Setting<String>,
+    pub keep_alive: Setting<KeepAlive>,
+    pub throttle_interval: Setting<u64>,
+    pub stop_signal: Setting<String>,
+    pub exit_timeout: Setting<u64>,
+    pub requires: Setting<Vec<String>>,
+    pub after: Setting<Vec<String>>,
+    pub health_check: Setting<Option<HealthCheck>>,
+}
+
+impl ServiceSettings {
+    /// Starts from the plist and the defaults, then applies `layers` in order, each
+    /// one field by field over the ones before it.
+    ///
+    /// The defaults are flint's, or the `log_dir` and `stop_timeout` of the global config.
+    pub fn resolve(
+        formula: &str,
+        launchd: &LaunchdConfig,
+        global: &GlobalConfig,
+        layers: &[(Origin, &Overrides)],
+    ) -> Result<Self> {
+        let (log_dir, log_dir_origin) = match global.log_dir() {
+            Some(dir) => (dir.to_path_buf(), Origin::Global),
+            None => {
+                let home = std::env::var("HOME")?;
+                (Path::new(&home).join("Library/Logs/Flint"), Origin::Default)
+            }
+        };
+        let from_plist = |value: Option<&str>, file_name: String| match value {
+            Some(value) => Setting::new(value.to_string(), Origin::Plist),
+            None => Setting::new(
+                log_dir.join(file_name).display().to_string(),
+                log_dir_origin,
+            ),
+        };
+
+        // `WorkingDirectory` defaults to "." when the plist leaves it out.
+        let working_directory = match launchd.working_directory() {
+            "." => Setting::new(".".to_string(), Origin::Default),
+            dir => Setting::new(dir.to_string(), Origin::Plist),
+        };
+
+        let mut settings = ServiceSettings {
+            program: Setting::new(launchd.program().to_string(), Origin::Plist),
+            args: Setting::new(launchd.args().to_vec(), Origin::Plist),
+            environment_variables: launchd
+                .environment_variables()
+                .iter()
+                .map(|(key, value)| (key.clone(), Setting::new(value.clone(), Origin::Plist)))
+                .collect(),
+            working_directory,
+            stdout_path: from_plist(launchd.stdout_path(), format!("{}.log", formula)),
+            stderr_path: from_plist(launchd.stderr_path(), format!("{}_error.log", formula)),
+            keep_alive: match launchd.keep_alive() {
+                Some(keep_alive) => Setting::new(keep_alive.clone(), Origin::Plist),
+                None => Setting::new(KeepAlive::Always(false), Origin::Default),
+            },
+            throttle_interval: match launchd.throttle_interval() {
+                Some(secs) => Setting::new(secs, Origin::Plist),
+                None => Setting::new(DEFAULT_THROTTLE_INTERVAL, Origin::Default),
+            },
+            stop_signal: Setting::new(service_process::signal_name(SIGTERM), Origin::Default),
+            exit_timeout: match (launchd.exit_timeout(), global.stop_timeout()) {
+                (Some(secs), _) => Setting::new(secs, Origin::Plist),
+                (None, Some(timeout)) => Setting::new(timeout.as_secs(), Origin::Global),
+                (None, None) => Setting::new(DEFAULT_EXIT_TIMEOUT, Origin::Default),
+            },
+            requires: Setting::new(Vec::new(), Origin::Default),
+            after: Setting::new(Vec::new(), Origin::Default),
+            health_check: Setting::new(None, Origin::Default),
+        };
+
+        for (origin, overrides) in layers {
+            settings.apply(*origin, overrides)?;
+        }
+        Ok(settings)
+    }
+
+    fn apply(&mut self, origin: Origin, overrides: &Overrides) -> Result<()> {
+        if let Some(signal) = &overrides.stop_signal
+            && service_process::parse_signal(signal).is_none()
+        {
+            return Err(Error::InvalidSignal {
+                signal: signal.clone(),
+            });
+        }
+        if let Some(health_check) = &overrides.health_check {
+            health_check.validate()?;
+        }
+
+        self.args.set(overrides.args.clone(), origin);
+        for (key, value) in &overrides.environment_variables {
+            self.environment_variables
+                .insert(key.clone(), Setting::new(value.clone(), origin));
+        }
+        self.working_directory
+            .set(overrides.working_directory.clone(), origin);
+        self.stdout_path
+            .set(overrides.standard_out_path.clone(), origin);
+        self.stderr_path
+            .set(overrides.standard_error_path.clone(), origin);
+        self.keep_alive.set(overrides.keep_alive.clone(), origin);
+        self.throttle_interval
+            .set(overrides.throttle_interval, origin);
+        self.stop_signal.set(overrides.stop_signal.clone(), origin);
+        self.exit_timeout.set(overrides.exit_timeout, origin);
+        self.requires.set(overrides.requires.clone(), origin);
+        self.after.set(overrides.after.clone(), origin);
+        self.health_check
+            .set(overrides.health_check.clone().map(Some), origin);
+        Ok(())
+    }
+
+    /// The effective environment variables, without their origins.
+    pub fn environment(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.environment_variables
+            .iter()
+            .map(|(key, setting)| (key.as_str(), setting.value.as_str()))
+    }
+
+    /// The stop signal as a number; it was validated when the layers were applied.
+    pub fn stop_signal_number(&self) -> c_int {
+        service_process::parse_signal(&self.stop_signal.value).unwrap_or(SIGTERM)
+    }
+
+    pub fn exit_timeout(&self) -> Duration {
+        Duration::from_secs(self.exit_timeout.value)
+    }
+
+    pub fn throttle_interval(&self) -> Duration {
+        Duration::from_secs(self.throttle_interval.value)
+    }
+}
@@ -0,0 +1,443 @@
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::global_config::GlobalConfig;
+use crate::project::Project;
+use crate::services::service_homebrew::ServiceHomebrew;
+use crate::services::service_native::NativeDefinition;
+use crate::services::service_user_agent::ServiceUserAgent;
+
+/// Agents installed for every user of the machine.
+pub const GLOBAL_AGENTS_DIR: &str = "/Library/LaunchAgents";
+
+/// System-wide daemons, which only root may start or stop.
+pub const GLOBAL_DAEMONS_DIR: &str = "/Library/LaunchDaemons";
+
+/// Environment variable listing extra plist directories, separated by `:`.
+///
+/// Each entry is either `<dir>` or `<name>=<dir>`; unnamed entries are named
+/// after their path. They are searched after the built-in sources, in order.
+pub const SOURCE_DIRS_ENV: &str = "FLINT_SOURCE_DIRS";
+
+/// A place services can be discovered from, such as Homebrew or a LaunchAgents directory.
+pub trait ServiceSource {
+    /// Name shown by `status` and matched by `--source`.
+    fn name(&self) -> &str;
+
+    /// Short names of every service this source provides.
+    fn discover(&self) -> Result<Vec<String>>;
+
+    /// Path of the plist describing `formula`, or `None` if this source does not have it.
+    fn plist_path(&self, formula: &str) -> Result<Option<PathBuf>>;
+
+    /// launchd label assumed for `formula` when its plist has no `Label`; the file stem by default.
+    fn label(&self, _formula: &str, plist_path: &Path) -> String {
+        plist_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Whether managing services of this source needs root privileges.
+    fn requires_root(&self) -> bool {
+        false
+    }
+
+    /// Resolves the service whose launchd `Label` is exactly `label`.
+    fn resolve_label(&self, label: &str) -> Result<Option<ResolvedService>>;
+
+    /// Resolves `formula` to a service of this source.
+    fn resolve(&self, formula: &str) -> Result<Option<ResolvedService>> {
+        let Some(plist_path) = self.plist_path(formula)? else {
+            return Ok(None);
+        };
+        Ok(Some(ResolvedService::new(self, formula, plist_path)))
+    }
+}
+
+/// A service located by a `ServiceSource`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedService {
+    formula: String,
+    source: String,
+    label: String,
+    plist_path: String,
+    requires_root: bool,
+}
+
+impl ResolvedService {
+    /// The service `formula` of `source`, identified by the `Label` of its plist.
+    fn new(source: &(impl ServiceSource + ?Sized), formula: &str, plist_path: PathBuf) -> Self {
+        let label = ServiceUserAgent::plist_label(&plist_path)
+            .unwrap_or_else(|| source.label(formula, &plist_path));
+        ResolvedService {
+            formula: formula.to_string(),
+            source: source.name().to_string(),
+            label,
+            plist_path: plist_path.display().to_string(),
+            requires_root: source.requires_root(),
+        }
+    }
+
+    /// Friendly short name, kept as an alias of the label.
+    pub fn formula(&self) -> &str {
+        &self.formula
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// launchd `Label`, the canonical identity of the service.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn formula_plist_path(&self) -> &str {
+        &self.plist_path
+    }
+
+    pub fn requires_root(&self) -> bool {
+        self.requires_root
+    }
+}
+
+/// Homebrew formulae under `<prefix>/opt`.
+pub struct HomebrewSource {
+    prefix: PathBuf,
+}
+
+impl HomebrewSource {
+    pub fn new(prefix: PathBuf) -> Self {
+        HomebrewSource { prefix }
+    }
+}
+
+impl ServiceSource for HomebrewSource {
+    fn name(&self) -> &str {
+        "Homebrew"
+    }
+
+    fn discover(&self) -> Result<Vec<String>> {
+        if !self.prefix.join("opt").is_dir() {
+            return Ok(Vec::new());
+        }
+        ServiceHomebrew::formulas(&self.prefix)
+    }
+
+    fn plist_path(&self, formula: &str) -> Result<Option<PathBuf>> {
+        match ServiceHomebrew::new(formula.to_string(), &self.prefix) {
+            Ok(homebrew) => Ok(Some(PathBuf::from(homebrew.formula_plist_path()))),
+            Err(Error::FormulaNotFound { .. } | Error::PlistNotFound { .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn label(&self, formula: &str, _plist_path: &Path) -> String {
+        format!("homebrew.mxcl.{}", formula)
+    }
+
+    fn resolve_label(&self, label: &str) -> Result<Option<ResolvedService>> {
+        let Some(formula) = label.strip_prefix("homebrew.mxcl.") else {
+            return Ok(None);
+        };
+        Ok(self
+            .resolve(formula)?
+            .filter(|service| service.label() == label))
+    }
+}
+
+/// Any directory of launchd plists, such as `~/Library/LaunchAgents`.
+pub struct LaunchDirSource {
+    name: String,
+    dir: PathBuf,
+    requires_root: bool,
+}
+
+impl LaunchDirSource {
+    pub fn new(name: impl Into<String>, dir: PathBuf) -> Self {
+        LaunchDirSource {
+            name: name.into(),
+            dir,
+            requires_root: false,
+        }
+    }
+
+    /// Marks the directory as holding daemons that only root may manage.
+    pub fn requiring_root(mut self) -> Self {
+        self.requires_root = true;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl ServiceSource for LaunchDirSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn discover(&self) -> Result<Vec<String>> {
+        if !self.dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        ServiceUserAgent::formulas_in(&self.dir)
+    }
+
+    fn plist_path(&self, formula: &str) -> Result<Option<PathBuf>> {
+        if !self.dir.is_dir() {
+            return Ok(None);
+        }
+        ServiceUserAgent::find_plist_in(&self.dir, formula)
+    }
+
+    fn resolve_label(&self, label: &str) -> Result<Option<ResolvedService>> {
+        if !self.dir.is_dir() {
+            return Ok(None);
+        }
+        let Some(plist_path) = ServiceUserAgent::find_plist_by_label_in(&self.dir, label)? else {
+            return Ok(None);
+        };
+        let formula = ServiceUserAgent::formula_of(&self.dir, &plist_path)?
+            .unwrap_or_else(|| label.to_string());
+        Ok(Some(ResolvedService::new(self, &formula, plist_path)))
+    }
+
+    fn requires_root(&self) -> bool {
+        self.requires_root
+    }
+}
+
+/// Native flint definitions, `<name>.toml` files such as those in
+/// `~/.config/flint/services`.
+pub struct NativeSource {
+    dir: PathBuf,
+}
+
+impl NativeSource {
+    pub fn new(dir: PathBuf) -> Self {
+        NativeSource { dir }
+    }
+
+    fn definitions(&self) -> Result<Vec<(String, PathBuf)>> {
+        if !self.dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        NativeDefinition::definitions_in(&self.dir)
+    }
+}
+
+impl ServiceSource for NativeSource {
+    fn name(&self) -> &str {
+        "Flint"
+    }
+
+    fn discover(&self) -> Result<Vec<String>> {
+        Ok(self
+            .definitions()?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect())
+    }
+
+    fn plist_path(&self, formula: &str) -> Result<Option<PathBuf>> {
+        Ok(self
+            .definitions()?
+            .into_iter()
+            .find(|(name, _)| name == formula)
+            .map(|(_, path)| path))
+    }
+
+    fn label(&self, formula: &str, plist_path: &Path) -> String {
+        NativeDefinition::load(plist_path)
+            .map(|definition| definition.label(formula))
+            .unwrap_or_else(|_| NativeDefinition::default_label(formula))
+    }
+
+    fn resolve_label(&self, label: &str) -> Result<Option<ResolvedService>> {
+        for (name, path) in self.definitions()? {
+            if self.label(&name, &path) == label {
+                return Ok(Some(ResolvedService::new(self, &name, path)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Services of the project of the current directory, named `<project>.<service>`.
+pub struct ProjectSource {
+    project: Project,
+}
+
+impl ProjectSource {
+    /// Name of the source, which also tells `ServiceManager` to read the project file.
+    pub const NAME: &str = "Project";
+
+    pub fn new(project: Project) -> Self {
+        ProjectSource { project }
+    }
+}
+
+impl ServiceSource for ProjectSource {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn discover(&self) -> Result<Vec<String>> {
+        Ok(self
+            .project
+            .service_names()
+            .map(|name| self.project.formula(name))
+            .collect())
+    }
+
+    fn plist_path(&self, formula: &str) -> Result<Option<PathBuf>> {
+        Ok(self
+            .project
+            .definition(formula)
+            .map(|_| self.project.file().to_path_buf()))
+    }
+
+    fn label(&self, formula: &str, _plist_path: &Path) -> String {
+        self.project
+            .definition(formula)
+            .map(|definition| definition.label(formula))
+            .unwrap_or_default()
+    }
+
+    fn resolve_label(&self, label: &str) -> Result<Option<ResolvedService>> {
+        for formula in self.discover()? {
+            if self.label(&formula, self.project.file()) == label {
+                return Ok(Some(ResolvedService::new(
+                    self,
+                    &formula,
+                    self.project.file().to_path_buf(),
+                )));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Ordered set of sources; earlier sources take precedence when names collide.
+#[derive(Default)]
+pub struct ServiceSources {
+    sources: Vec<Box<dyn ServiceSource>>,
+}
+
+impl ServiceSources {
+    /// The project of the current directory, if any, native definitions in
+    /// `~/.config/flint/services`, Homebrew, `~/Library/LaunchAgents`,
+    /// `/Library/LaunchAgents`, `/Library/LaunchDaemons`, then the directories in
+    /// `FLINT_SOURCE_DIRS`.
+    ///
+    /// The Homebrew prefix of the global config is used unless `$HOMEBREW_PREFIX` is set.
+    pub fn from_env(config: &GlobalConfig) -> Result<Self> {
+        let mut sources = ServiceSources::default();
+        if let Some(project) = Project::find(&std::env::current_dir()?)? {
+            sources.register(ProjectSource::new(project));
+        }
+        sources.register(NativeSource::new(NativeDefinition::services_dir()?));
+        if let Some(prefix) = ServiceHomebrew::resolve_prefix(config.homebrew_prefix()) {
+            sources.register(HomebrewSource::new(prefix));
+        }
+        sources.register(LaunchDirSource::new(
+            "UserAgent",
+            ServiceUserAgent::launch_agents_dir()?,
+        ));
+        sources.register(LaunchDirSource::new(
+            "GlobalAgent",
+            PathBuf::from(GLOBAL_AGENTS_DIR),
+        ));
+        sources.register(
+            LaunchDirSource::new("GlobalDaemon", PathBuf::from(GLOBAL_DAEMONS_DIR))
+                .requiring_root(),
+        );
+
+        if let Some(dirs) = std::env::var_os(SOURCE_DIRS_ENV) {
+            for entry in dirs.to_string_lossy().split(':').filter(|e| !e.is_empty()) {
+                let (name, dir) = entry.split_once('=').unwrap_or((entry, entry));
+                sources.register(LaunchDirSource::new(name, PathBuf::from(dir)));
+            }
+        }
+        Ok(sources)
+    }
+
+    /// Adds a source with lower precedence than the ones already registered.
+    pub fn register(&mut self, source: impl ServiceSource + 'static) {
+        self.sources.push(Box::new(source));
+    }
+
+    pub fn sources(&self) -> impl Iterator<Item = &dyn ServiceSource> {
+        self.sources.iter().map(|source| source.as_ref())
+    }
+
+    /// Finds a source by name, ignoring case.
+    pub fn get(&self, name: &str) -> Option<&dyn ServiceSource> {
+        self.sources()
+            .find(|source| source.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Resolves `formula` in the first source that has it, or only in `forced` if given.
+    ///
+    /// A source matches on the exact launchd `Label` first, then on a short name.
+    pub fn resolve(&self, formula: &str, forced: Option<&str>) -> Result<ResolvedService> {
+        for source in self.selected(forced)? {
+            if let Some(service) = source.resolve_label(formula)? {
+                return Ok(service);
+            }
+            if let Some(service) = source.resolve(formula)? {
+                return Ok(service);
+            }
+        }
+        Err(Error::FormulaNotFound {
+            formula: formula.to_string(),
+        })
+    }
+
+    /// Resolves the service whose launchd `Label` is exactly `label`, like `resolve`.
+    pub fn resolve_label(&self, label: &str, forced: Option<&str>) -> Result<ResolvedService> {
+        for source in self.selected(forced)? {
+            if let Some(service) = source.resolve_label(label)? {
+                return Ok(service);
+            }
+        }
+        Err(Error::FormulaNotFound {
+            formula: label.to_string(),
+        })
+    }
+
+    /// Every discoverable service, grouped by source in precedence order.
+    ///
+    /// With `forced`, only that source is scanned. A label provided by several
+    /// sources is listed once, from the source that takes precedence.
+    pub fn discover(&self, forced: Option<&str>) -> Result<Vec<ResolvedService>> {
+        let mut services: Vec<ResolvedService> = Vec::new();
+        for source in self.selected(forced)? {
+            for formula in source.discover()? {
+                let Some(service) = source.resolve(&formula)? else {
+                    continue;
+                };
+                if !services.iter().any(|seen| seen.label() == service.label()) {
+                    services.push(service);
+                }
+            }
+        }
+        Ok(services)
+    }
+
+    /// All sources, or only the one named `forced`.
+    fn selected(&self, forced: Option<&str>) -> Result<Vec<&dyn ServiceSource>> {
+        match forced {
+            None => Ok(self.sources().collect()),
+            Some(name) => {
+                let source = self.get(name).ok_or_else(|| Error::UnknownSource {
+                    source_name: name.to_string(),
+                })?;
+                Ok(vec![source])
+            }
+        }
+    }
+}
@@ -1,27 +1,209 @@
-use crate::{error::Result, launchd_config::LaunchdConfig};
+use crate::{
+    error::Result,
+    launchd_config::LaunchdConfig,
+    services::service_process::{self, SignalTarget},
+};
+use nix::fcntl::{Flock, FlockArg};
+use nix::unistd::Uid;
 use serde::{Deserialize, Serialize};
-use std::{fs, io::Write, path::PathBuf, process::Command};
+use std::{
+    fs,
+    io::Write,
+    os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt},
+    path::{Path, PathBuf},
+    process::Command,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Environment variable overriding the directory state files are kept in.
+pub const STATE_DIR_ENV: &str = "FLINT_STATE_DIR";
+
+/// Shared directory used by older versions, migrated from on first run.
+pub const LEGACY_STATE_DIR: &str = "/tmp/flint";
 
 /// Represents the running status of a service.
 #[derive(Debug, Deserialize, Clone, Serialize, PartialEq, Eq)]
 pub enum ServiceStatus {
     Running,
+    /// Stopped on purpose through flint.
     Stopped,
+    /// No trustworthy record, e.g. never started by flint or its PID was recycled.
     Stale,
+    /// Ended on its own with exit code 0.
+    Exited,
+    /// Ended on its own with a failure, or vanished without flint seeing it exit.
+    Crashed,
+}
+
+/// Version of the state file layout, bumped whenever its meaning changes.
+///
+/// Files without a version predate versioning and are read as version 0.
+pub const STATE_SCHEMA_VERSION: u32 = 1;
+
+/// How many lifecycle events are kept in a state file.
+pub const HISTORY_LIMIT: usize = 20;
+
+/// Which flint command started the current run of a service.
+#[derive(Debug, Deserialize, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StartedBy {
+    Start,
+    Restart,
+    Supervise,
+    Up,
+    Adopt,
+}
+
+impl std::fmt::Display for StartedBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let command = match self {
+            StartedBy::Start => "start",
+            StartedBy::Restart => "restart",
+            StartedBy::Supervise => "supervise",
+            StartedBy::Up => "up",
+            StartedBy::Adopt => "adopt",
+        };
+        write!(f, "{command}")
+    }
+}
+
+/// Something that happened to a service, as recorded in its history.
+#[derive(Debug, Deserialize, Clone, Serialize, PartialEq, Eq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum LifecycleEvent {
+    Started {
+        pid: i32,
+        by: StartedBy,
+    },
+    Restarted {
+        pid: i32,
+    },
+    Adopted {
+        pids: Vec<i32>,
+    },
+    Exited {
+        code: Option<i32>,
+        signal: Option<i32>,
+    },
+    /// The recorded processes were found gone or running another program.
+    Vanished,
+    Stopped,
+}
+
+/// A lifecycle event and the unix timestamp, in seconds, at which it happened.
+#[derive(Debug, Deserialize, Clone, Serialize, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub at: u64,
+    #[serde(flatten)]
+    pub event: LifecycleEvent,
 }
 
 /// Stores information about a service instance, including its PID and status.
 #[derive(Debug, Deserialize, Clone, Serialize, PartialEq, Eq)]
 pub struct ServiceStateData {
+    #[serde(default)]
+    schema_version: u32,
     pids: Vec<i32>,
     status: ServiceStatus,
+    #[serde(default)]
+    restarts: u32,
+    /// Process group of the spawned child, when flint spawned it.
+    #[serde(default)]
+    pgid: Option<i32>,
+    /// Start time token of the spawned child, used to detect recycled PIDs.
+    #[serde(default)]
+    process_start: Option<String>,
+    /// Unix timestamp, in seconds, at which flint spawned the service.
+    #[serde(default)]
+    started_at: Option<u64>,
+    #[serde(default)]
+    started_by: Option<StartedBy>,
+    /// Exit code of the last run, when flint observed it exiting.
+    #[serde(default)]
+    last_exit_code: Option<i32>,
+    /// Signal that ended the last run, when flint observed or sent it.
+    #[serde(default)]
+    last_exit_signal: Option<i32>,
+    /// Unix timestamp, in seconds, at which the last run ended.
+    #[serde(default)]
+    last_exit_at: Option<u64>,
+    /// The last `HISTORY_LIMIT` lifecycle events, oldest first.
+    #[serde(default)]
+    history: Vec<HistoryEntry>,
 }
 
 impl ServiceStateData {
-    // used in tests
-    #[allow(dead_code)]
     pub fn new(pids: Vec<i32>, status: ServiceStatus) -> Self {
-        Self { pids, status }
+        Self {
+            schema_version: STATE_SCHEMA_VERSION,
+            pids,
+            status,
+            restarts: 0,
+            pgid: None,
+            process_start: None,
+            started_at: None,
+            started_by: None,
+            last_exit_code: None,
+            last_exit_signal: None,
+            last_exit_at: None,
+            history: Vec::new(),
+        }
+    }
+
+    /// Records a child flint just spawned as leader of its own process group.
+    ///
+    /// The history and the outcome of the previous run are kept.
+    fn spawned(self, pid: i32, restarts: u32, started_by: StartedBy) -> Self {
+        Self {
+            pids: vec![pid],
+            status: ServiceStatus::Running,
+            restarts,
+            pgid: Some(pid),
+            process_start: service_process::start_time(pid),
+            started_at: Some(unix_now()),
+            started_by: Some(started_by),
+            ..self
+        }
+    }
+
+    /// Records processes taken over from outside flint as the running service.
+    fn adopted(self, pids: Vec<i32>) -> Self {
+        Self {
+            pids: pids.clone(),
+            status: ServiceStatus::Running,
+            restarts: 0,
+            pgid: None,
+            process_start: None,
+            started_at: None,
+            started_by: Some(StartedBy::Adopt),
+            ..self
+        }
+        .record(LifecycleEvent::Adopted { pids })
+    }
+
+    /// Appends `event` to the history, dropping the oldest entries past `HISTORY_LIMIT`.
+    fn record(mut self, event: LifecycleEvent) -> Self {
+        self.history.push(HistoryEntry {
+            at: unix_now(),
+            event,
+        });
+        let excess = self.history.len().saturating_sub(HISTORY_LIMIT);
+        self.history.drain(..excess);
+        self
+    }
+
+    /// Brings a state read from an older layout up to `STATE_SCHEMA_VERSION`.
+    ///
+    /// Version 0 only lacks fields that default sensibly, so nothing else changes yet.
+    fn migrate(mut self) -> Self {
+        if self.schema_version < STATE_SCHEMA_VERSION {
+            self.schema_version = STATE_SCHEMA_VERSION;
+        }
+        self
+    }
+
+    pub fn schema_version(&self) -> u32 {
+        self.schema_version
     }
 
     pub fn status(&self) -> &ServiceStatus {
@@ -32,102 +214,477 @@ impl ServiceStateData {
         &self.pids
     }
 
-    pub fn to_string(&self) -> String {
-        match &self.status {
-            ServiceStatus::Running => String::from("Running"),
-            ServiceStatus::Stopped => String::from("Stopped"),
-            ServiceStatus::Stale => String::from("Stale"),
+    /// Process group id of the tracked child, if flint spawned it.
+    pub fn pgid(&self) -> Option<i32> {
+        self.pgid
+    }
+
+    /// What to signal to reach the service: its process group when flint spawned
+    /// it, so forked workers are included, or else each recorded PID.
+    pub fn signal_targets(&self) -> Vec<SignalTarget> {
+        match self.pgid {
+            Some(pgid) => vec![SignalTarget::Group(pgid)],
+            None => self
+                .pids
+                .iter()
+                .map(|pid| SignalTarget::Process(*pid))
+                .collect(),
+        }
+    }
+
+    /// Whether the recorded PID still refers to the process flint spawned.
+    ///
+    /// States without a recorded start time (e.g. adopted processes) cannot be
+    /// verified and are trusted as-is.
+    fn is_tracked_process_alive(&self) -> bool {
+        match (&self.process_start, self.pids.first()) {
+            (Some(recorded), Some(pid)) => {
+                service_process::start_time(*pid).as_ref() == Some(recorded)
+            }
+            _ => true,
         }
     }
+
+    /// Number of times the supervisor restarted the service since it was started.
+    pub fn restarts(&self) -> u32 {
+        self.restarts
+    }
+
+    /// Unix timestamp, in seconds, at which flint spawned the service.
+    pub fn started_at(&self) -> Option<u64> {
+        self.started_at
+    }
+
+    pub fn started_by(&self) -> Option<StartedBy> {
+        self.started_by
+    }
+
+    /// How long the service has been running, if it is running and flint spawned it.
+    pub fn uptime(&self) -> Option<Duration> {
+        if self.status != ServiceStatus::Running {
+            return None;
+        }
+        self.started_at
+            .map(|started_at| Duration::from_secs(unix_now().saturating_sub(started_at)))
+    }
+
+    pub fn last_exit_code(&self) -> Option<i32> {
+        self.last_exit_code
+    }
+
+    pub fn last_exit_signal(&self) -> Option<i32> {
+        self.last_exit_signal
+    }
+
+    /// How long ago the last run ended, if flint saw it end.
+    pub fn since_last_exit(&self) -> Option<Duration> {
+        self.last_exit_at
+            .map(|exited_at| Duration::from_secs(unix_now().saturating_sub(exited_at)))
+    }
+
+    pub fn history(&self) -> &[HistoryEntry] {
+        &self.history
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+impl std::fmt::Display for ServiceStateData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match &self.status {
+            ServiceStatus::Running => "Running",
+            ServiceStatus::Stopped => "Stopped",
+            ServiceStatus::Stale => "Stale",
+            ServiceStatus::Exited => "Exited",
+            ServiceStatus::Crashed => "Crashed",
+        };
+        write!(f, "{status}")
+    }
 }
 
 /// Manages reading and writing of a service’s runtime state to disk.
 pub struct ServiceState {
-    formula: String,
+    label: String,
+    alias: Option<String>,
     binary_name: String,
+    program: PathBuf,
+    configured_dir: Option<PathBuf>,
 }
 
 impl ServiceState {
-    /// Creates a new `ServiceState` associated with the given formula.
-    pub fn new(formula: impl Into<String>, launchd_config: &LaunchdConfig) -> Self {
+    /// Creates a new `ServiceState` for the service with the given launchd label.
+    pub fn new(label: impl Into<String>, launchd_config: &LaunchdConfig) -> Self {
         Self {
-            formula: formula.into(),
+            label: label.into(),
+            alias: None,
             binary_name: launchd_config.binary_name(),
+            program: PathBuf::from(launchd_config.program()),
+            configured_dir: None,
         }
     }
 
+    /// State directory from the global config, used unless `$FLINT_STATE_DIR` is set.
+    pub fn with_state_dir(mut self, dir: Option<&Path>) -> Self {
+        self.configured_dir = dir.map(Path::to_path_buf);
+        self
+    }
+
+    /// Short name the state used to be stored under, picked up if no state exists for the label.
+    pub fn with_alias(mut self, alias: impl Into<String>) -> Self {
+        self.alias = Some(alias.into()).filter(|alias| *alias != self.label);
+        self
+    }
+
+    /// Finds PIDs by binary name.
+    ///
+    /// This may match unrelated processes sharing the name, so it is only used
+    /// when explicitly adopting a process flint did not spawn.
     pub fn search_pids(&self) -> Result<Vec<i32>> {
-        let output = Command::new("pgrep")
-            .args([self.binary_name.as_str()])
-            .output()?;
+        Ok(service_process::find_by_name(&self.binary_name)?)
+    }
+
+    /// Directory holding the state files of the current user.
+    ///
+    /// `$FLINT_STATE_DIR` if set, else `~/Library/Application Support/flint` on
+    /// macOS and `$XDG_STATE_HOME/flint` (default `~/.local/state/flint`) elsewhere.
+    /// It is created private to the user; the default one is seeded from
+    /// `/tmp/flint` when it is first created.
+    pub fn state_dir() -> Result<PathBuf> {
+        Self::state_dir_in(None)
+    }
 
-        if !output.status.success() {
-            return Ok(Vec::new());
+    /// Like `state_dir`, with the `configured` directory taking the place of the
+    /// platform default.
+    pub fn state_dir_in(configured: Option<&Path>) -> Result<PathBuf> {
+        if let Some(dir) = std::env::var_os(STATE_DIR_ENV)
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| configured.map(Path::to_path_buf))
+        {
+            create_private_dir(&dir)?;
+            return Ok(dir);
         }
 
-        let pids = String::from_utf8_lossy(&output.stdout)
-            .lines()
-            .filter_map(|line| line.trim().parse::<i32>().ok())
-            .collect();
-        Ok(pids)
+        let dir = default_state_dir()?;
+        let first_run = !dir.exists();
+        create_private_dir(&dir)?;
+        if first_run {
+            migrate_state_files(Path::new(LEGACY_STATE_DIR), &dir)?;
+        }
+        Ok(dir)
     }
 
     /// Returns the path to the JSON file that stores this service's state.
-    fn state_file_path(&self) -> Result<PathBuf> {
-        let dir = PathBuf::from("/tmp/flint");
-        fs::create_dir_all(&dir)?;
-        Ok(dir.join(format!("{}.state.json", self.formula)))
+    ///
+    /// A state file left under the short alias by older versions is moved to the label.
+    pub fn state_file_path(&self) -> Result<PathBuf> {
+        let dir = Self::state_dir_in(self.configured_dir.as_deref())?;
+        let path = dir.join(format!("{}.state.json", self.label));
+        if let Some(alias) = &self.alias {
+            let legacy = dir.join(format!("{}.state.json", alias));
+            if !path.exists() && legacy.exists() {
+                fs::rename(&legacy, &path)?;
+            }
+        }
+        Ok(path)
+    }
+
+    /// Records the child flint just spawned for `started_by` and marks the service as running.
+    pub fn mark_spawned(&self, pid: i32, started_by: StartedBy) -> Result<()> {
+        self.update(|data| {
+            data.spawned(pid, 0, started_by)
+                .record(LifecycleEvent::Started {
+                    pid,
+                    by: started_by,
+                })
+        })
     }
 
-    /// Writes the given PID and marks the service as running.
+    /// Writes the given PIDs, taken over from outside flint, and marks the service as running.
     pub fn mark_running(&self, pids: Vec<i32>) -> Result<()> {
-        let data = ServiceStateData {
-            pids,
-            status: ServiceStatus::Running,
-        };
+        self.update(|data| data.adopted(pids))
+    }
+
+    /// Records a supervisor restart with the new child and bumps the restart counter.
+    pub fn mark_restarted(&self, pid: i32) -> Result<()> {
+        self.update(|data| {
+            let restarts = data.restarts + 1;
+            let started_by = data.started_by.unwrap_or(StartedBy::Supervise);
+            data.spawned(pid, restarts, started_by)
+                .record(LifecycleEvent::Restarted { pid })
+        })
+    }
+
+    /// Read-modify-write of the state under an exclusive lock, so concurrent
+    /// flint invocations cannot lose each other's updates.
+    fn update(&self, change: impl FnOnce(ServiceStateData) -> ServiceStateData) -> Result<()> {
+        let _lock = self.lock()?;
+        let data = change(self.read_state()?);
+        self.write_state(&data)
+    }
+
+    /// Takes the advisory lock guarding this service's state; released on drop.
+    fn lock(&self) -> Result<Flock<fs::File>> {
+        let path = self.state_file_path()?.with_extension("lock");
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)?;
+        Flock::lock(file, FlockArg::LockExclusive)
+            .map_err(|(_, errno)| std::io::Error::from(errno).into())
+    }
 
+    /// Writes the state to a temporary file then renames it over the state file,
+    /// so readers never see a partially written state.
+    fn write_state(&self, data: &ServiceStateData) -> Result<()> {
         let path = self.state_file_path()?;
-        let mut file = fs::File::create(path)?;
-        serde_json::to_writer_pretty(&mut file, &data)?;
+        let tmp_path = path.with_extension(format!("json.{}.tmp", std::process::id()));
+        let mut file = fs::File::create(&tmp_path)?;
+        serde_json::to_writer_pretty(&mut file, data)?;
         file.flush()?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &path)?;
         Ok(())
     }
 
     /// Reads the service’s state data from disk.
+    ///
+    /// A running state whose PID no longer belongs to the spawned child is
+    /// reported as `Stale`, so a recycled PID is never mistaken for ours. A
+    /// corrupt state file is set aside and rebuilt from the live processes.
     pub fn read_state(&self) -> Result<ServiceStateData> {
-        let data = match fs::read_to_string(self.state_file_path()?) {
-            Ok(contents) => serde_json::from_str(&contents)?,
-            Err(_) => ServiceStateData {
+        let data = self.read_recorded()?;
+        if data.status == ServiceStatus::Running && !data.is_tracked_process_alive() {
+            return Ok(ServiceStateData {
                 pids: Vec::new(),
                 status: ServiceStatus::Stale,
+                pgid: None,
+                process_start: None,
+                started_at: None,
+                ..data
+            });
+        }
+        Ok(data)
+    }
+
+    /// The state as recorded on disk, without checking any process.
+    fn read_recorded(&self) -> Result<ServiceStateData> {
+        let path = self.state_file_path()?;
+        let data = match fs::read_to_string(&path) {
+            Ok(contents) => match serde_json::from_str::<ServiceStateData>(&contents) {
+                Ok(data) => data.migrate(),
+                Err(err) => self.recover_corrupt_state(&path, &err)?,
             },
+            Err(_) => ServiceStateData::new(Vec::new(), ServiceStatus::Stale),
         };
         Ok(data)
     }
 
+    /// Checks the recorded state against the live processes and saves the outcome.
+    ///
+    /// Recorded PIDs that are gone, zombies, recycled or running another program
+    /// are dropped; if none is left the service is marked `Crashed`. With `adopt`,
+    /// a service that is not running takes over live processes of its program.
+    pub fn reconcile(&self, adopt: bool) -> Result<ServiceStateData> {
+        let _lock = self.lock()?;
+        let recorded = self.read_recorded()?;
+        let mut data = recorded.clone();
+
+        if data.status == ServiceStatus::Running {
+            let live: Vec<i32> = if data.is_tracked_process_alive() {
+                data.pids
+                    .iter()
+                    .copied()
+                    .filter(|pid| service_process::runs_program(*pid, &self.program) != Some(false))
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            if live.is_empty() {
+                data = ServiceStateData {
+                    pids: Vec::new(),
+                    status: ServiceStatus::Crashed,
+                    pgid: None,
+                    process_start: None,
+                    started_at: None,
+                    last_exit_at: Some(unix_now()),
+                    ..data
+                }
+                .record(LifecycleEvent::Vanished);
+            } else {
+                data.pids = live;
+            }
+        }
+
+        if adopt && data.status != ServiceStatus::Running {
+            let own_pid = std::process::id() as i32;
+            let pids: Vec<i32> = self
+                .search_pids()?
+                .into_iter()
+                .filter(|pid| {
+                    *pid != own_pid
+                        && service_process::runs_program(*pid, &self.program) == Some(true)
+                })
+                .collect();
+            if !pids.is_empty() {
+                data = data.adopted(pids);
+            }
+        }
+
+        if data != recorded {
+            self.write_state(&data)?;
+        }
+        Ok(data)
+    }
+
+    /// Moves an unreadable state file aside and infers the state from live processes.
+    ///
+    /// Processes matching the binary name are not adopted, since they may be
+    /// unrelated; the service is reported `Stale` so `flint adopt` can be used.
+    fn recover_corrupt_state(
+        &self,
+        path: &Path,
+        err: &serde_json::Error,
+    ) -> Result<ServiceStateData> {
+        let corrupt_path = path.with_extension("json.corrupt");
+        fs::rename(path, &corrupt_path)?;
+        eprintln!(
+            "State of '{}' was corrupt ({}), moved to {}.",
+            self.label,
+            err,
+            corrupt_path.display()
+        );
+
+        let status = if self.search_pids()?.is_empty() {
+            ServiceStatus::Stopped
+        } else {
+            ServiceStatus::Stale
+        };
+        Ok(ServiceStateData::new(Vec::new(), status))
+    }
+
     /// Marks the service as stopped and updates the state file.
     pub fn mark_stopped(&self) -> Result<()> {
-        let mut data = self.read_state()?;
-        data.status = ServiceStatus::Stopped;
-        let path = self.state_file_path()?;
-        let mut file = fs::File::create(path)?;
-        serde_json::to_writer_pretty(&mut file, &data)?;
-        file.flush()?;
-        Ok(())
+        self.update(|mut data| {
+            data.status = ServiceStatus::Stopped;
+            data.last_exit_at = Some(unix_now());
+            data.record(LifecycleEvent::Stopped)
+        })
+    }
+
+    /// Records that the service ended on its own with `code` or was ended by `signal`.
+    ///
+    /// A zero exit code is `Exited`; anything else is `Crashed`.
+    pub fn mark_exited(&self, code: Option<i32>, signal: Option<i32>) -> Result<()> {
+        self.update(|mut data| {
+            data.status = if code == Some(0) {
+                ServiceStatus::Exited
+            } else {
+                ServiceStatus::Crashed
+            };
+            data.last_exit_code = code;
+            data.last_exit_signal = signal;
+            data.last_exit_at = Some(unix_now());
+            data.record(LifecycleEvent::Exited { code, signal })
+        })
     }
 
-    // used in tests
-    #[allow(dead_code)]
-    pub fn formula(&self) -> &str {
-        &self.formula
+    /// Marks the service as stopped after flint sent it `signal` and it went away.
+    pub fn mark_killed(&self, signal: i32) -> Result<()> {
+        self.update(|mut data| {
+            data.status = ServiceStatus::Stopped;
+            data.last_exit_code = None;
+            data.last_exit_signal = Some(signal);
+            data.last_exit_at = Some(unix_now());
+            data.record(LifecycleEvent::Exited {
+                code: None,
+                signal: Some(signal),
+            })
+        })
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
     }
 
     /// Checks if the service is managed by launchctl (launchd)
     pub fn is_managed_by_launchctl(&self) -> Result<bool> {
-        let output = Command::new("launchctl").arg("list").output()?;
+        // Without launchctl (e.g. on Linux) nothing can be managed by launchd.
+        let Ok(output) = Command::new("launchctl").arg("list").output() else {
+            return Ok(false);
+        };
 
         let stdout = String::from_utf8_lossy(&output.stdout);
-        // Homebrew launchd labels usually look like: homebrew.mxcl.<formula>
-        Ok(stdout.contains(&format!("homebrew.mxcl.{}", self.formula)))
+        Ok(is_label_listed(&stdout, &self.label))
+    }
+}
+
+fn default_state_dir() -> Result<PathBuf> {
+    let home = PathBuf::from(std::env::var("HOME")?);
+    if cfg!(target_os = "macos") {
+        return Ok(home
+            .join("Library")
+            .join("Application Support")
+            .join("flint"));
     }
+    let state_home = std::env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.join(".local").join("state"));
+    Ok(state_home.join("flint"))
+}
+
+/// Creates `dir` if needed and restricts it to the current user.
+fn create_private_dir(dir: &Path) -> Result<()> {
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)?;
+    fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+    Ok(())
+}
+
+/// Moves the `*.state.json` files of `from` into `to`, returning how many moved.
+///
+/// Only files owned by the current user are taken, since anyone can write to
+/// `/tmp/flint`; files already present in `to` are left alone.
+pub fn migrate_state_files(from: &Path, to: &Path) -> Result<usize> {
+    let Ok(entries) = fs::read_dir(from) else {
+        return Ok(0);
+    };
+    let uid = Uid::effective().as_raw();
+
+    let mut migrated = 0;
+    for entry in entries.filter_map(|e| e.ok()) {
+        let name = entry.file_name();
+        let is_state = name.to_string_lossy().ends_with(".state.json");
+        let is_ours = entry
+            .metadata()
+            .is_ok_and(|meta| meta.is_file() && meta.uid() == uid);
+        let target = to.join(&name);
+        if !is_state || !is_ours || target.exists() {
+            continue;
+        }
+        // rename fails across filesystems, and /tmp often is one.
+        if fs::rename(entry.path(), &target).is_err() {
+            fs::copy(entry.path(), &target)?;
+            fs::remove_file(entry.path())?;
+        }
+        migrated += 1;
+    }
+    Ok(migrated)
+}
+
+/// Whether `launchctl list` output, one `PID<TAB>Status<TAB>Label` row per job, has `label`.
+pub fn is_label_listed(launchctl_list: &str, label: &str) -> bool {
+    launchctl_list
+        .lines()
+        .filter_map(|line| line.split('\t').nth(2))
+        .any(|listed| listed.trim() == label)
 }
@@ -16,19 +16,84 @@ impl ServiceUserAgent {
     }
 
     pub fn find_plist(formula: &str) -> Result<Option<PathBuf>> {
-        let formula_path = Self::launch_agents_dir()?;
+        Self::find_plist_in(&Self::launch_agents_dir()?, formula)
+    }
+
+    /// Looks for the plist of `formula` in any directory of launchd plists.
+    ///
+    /// A plist whose `Label` or file stem equals `formula` wins; otherwise the
+    /// shortened name from `transform` is tried. More than one match at the
+    /// same tier is reported as `AmbiguousFormula` rather than picked at random.
+    pub fn find_plist_in(dir: &Path, formula: &str) -> Result<Option<PathBuf>> {
+        let plists = Self::plists_in(dir)?;
+
+        let exact: Vec<_> = plists
+            .iter()
+            .filter(|path| {
+                Self::file_stem(path) == formula
+                    || Self::plist_label(path).as_deref() == Some(formula)
+            })
+            .collect();
+        if !exact.is_empty() {
+            return Self::single_match(formula, exact);
+        }
+
+        let shortened: Vec<_> = plists
+            .iter()
+            .filter(|path| Self::transform(&Self::file_stem(path)) == formula)
+            .collect();
+        Self::single_match(formula, shortened)
+    }
+
+    /// Looks for the plist whose `Label` is exactly `label`.
+    pub fn find_plist_by_label_in(dir: &Path, label: &str) -> Result<Option<PathBuf>> {
+        let matches: Vec<_> = Self::plists_in(dir)?
+            .into_iter()
+            .filter(|path| Self::plist_label(path).as_deref() == Some(label))
+            .collect();
+        Self::single_match(label, matches.iter().collect())
+    }
+
+    fn single_match(formula: &str, matches: Vec<&PathBuf>) -> Result<Option<PathBuf>> {
+        match matches.as_slice() {
+            [] => Ok(None),
+            [path] => Ok(Some(path.to_path_buf())),
+            _ => Err(Error::AmbiguousFormula {
+                formula: formula.to_string(),
+                candidates: matches
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect(),
+            }),
+        }
+    }
 
-        for entry in std::fs::read_dir(&formula_path)? {
-            let entry = entry?;
-            let file_name = entry.file_name();
-            let file_name_str = file_name.to_string_lossy();
+    /// The `.plist` files of a directory, sorted by name so lookups are deterministic.
+    fn plists_in(dir: &Path) -> Result<Vec<PathBuf>> {
+        let mut plists: Vec<_> = std::fs::read_dir(dir)?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "plist"))
+            .collect();
+        plists.sort();
+        Ok(plists)
+    }
 
-            if file_name_str.ends_with(&format!("{}.plist", formula)) {
-                return Ok(Some(entry.path()));
-            }
+    fn file_stem(path: &Path) -> String {
+        path.file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Reads only the `Label` key of a plist, ignoring unreadable files.
+    pub fn plist_label(path: &Path) -> Option<String> {
+        #[derive(serde::Deserialize)]
+        struct LabelOnly {
+            #[serde(rename = "Label")]
+            label: Option<String>,
         }
 
-        Ok(None)
+        plist::from_file::<_, LabelOnly>(path).ok()?.label
     }
 
     pub fn new(formula: String) -> Result<Self> {
@@ -63,22 +128,50 @@ impl ServiceUserAgent {
     }
 
     pub fn formulas() -> Result<Vec<String>> {
-        let path = Self::launch_agents_dir()?;
+        Self::formulas_in(&Self::launch_agents_dir()?)
+    }
 
-        let formulas: Vec<_> = std::fs::read_dir(path)?
-            .filter_map(|e| e.ok())
-            .map(|e| e.file_name())
-            .map(|f| f.display().to_string())
-            .filter_map(|plist| {
-                let formula = Self::transform(&plist);
-                if formula.starts_with("mxcl.") {
-                    None
+    /// Lists the short names of the plists in a directory, skipping Homebrew's.
+    ///
+    /// Plists whose shortened names collide are listed by their full file stem
+    /// instead, so every name resolves back to exactly one plist.
+    pub fn formulas_in(dir: &Path) -> Result<Vec<String>> {
+        let formulas = Self::names_in(dir)?
+            .into_iter()
+            .map(|(_, formula)| formula)
+            .collect();
+        Ok(formulas)
+    }
+
+    /// Short name of the plist at `path`, as listed by `formulas_in`.
+    pub fn formula_of(dir: &Path, path: &Path) -> Result<Option<String>> {
+        let formula = Self::names_in(dir)?
+            .into_iter()
+            .find(|(plist, _)| plist == path)
+            .map(|(_, formula)| formula);
+        Ok(formula)
+    }
+
+    /// Each plist of a directory with its short name, skipping Homebrew's.
+    fn names_in(dir: &Path) -> Result<Vec<(PathBuf, String)>> {
+        let plists = Self::plists_in(dir)?;
+        let stems: Vec<_> = plists.iter().map(|path| Self::file_stem(path)).collect();
+        let short_names: Vec<_> = stems.iter().map(|stem| Self::transform(stem)).collect();
+
+        let names = plists
+            .into_iter()
+            .zip(stems)
+            .zip(&short_names)
+            .filter(|(_, short)| !short.starts_with("mxcl."))
+            .map(|((path, stem), short)| {
+                if short_names.iter().filter(|other| *other == short).count() > 1 {
+                    (path, stem)
                 } else {
-                    Some(formula)
+                    (path, short.clone())
                 }
             })
             .collect();
-        Ok(formulas)
+        Ok(names)
     }
 
     pub fn formula_plist_path(&self) -> &str {
@@ -0,0 +1,377 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::launchd_config::KeepAlive;
+use crate::services::service_health::{Health, HealthCheck};
+use crate::services::service_settings::{ServiceSettings, Setting};
+use crate::services::service_state::{HistoryEntry, LifecycleEvent, ServiceStatus, StartedBy};
+
+/// Output format for `status` and `list`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Yaml,
+}
+
+/// Snapshot of one service, as exposed to scripts and other tools.
+///
+/// Field names are part of flint's output contract: add fields, don't rename them.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StatusReport {
+    pub formula: String,
+    #[serde(default)]
+    pub label: String,
+    pub source: String,
+    pub plist_path: String,
+    pub status: ServiceStatus,
+    pub pids: Vec<i32>,
+    pub uptime_secs: Option<u64>,
+    pub stdout_path: String,
+    pub stderr_path: String,
+    pub last_exit_code: Option<i32>,
+    #[serde(default)]
+    pub requires_root: bool,
+    /// Unix timestamp, in seconds, at which flint started the current run.
+    #[serde(default)]
+    pub started_at: Option<u64>,
+    #[serde(default)]
+    pub started_by: Option<StartedBy>,
+    #[serde(default)]
+    pub restarts: u32,
+    #[serde(default)]
+    pub last_exit_signal: Option<i32>,
+    #[serde(default)]
+    pub last_exit_secs_ago: Option<u64>,
+    /// Result of the health check, for running services that have one.
+    #[serde(default)]
+    pub health: Option<Health>,
+    /// Lifecycle events, oldest first; only filled in by `info`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<HistoryEntry>,
+}
+
+/// Renders a single service report.
+pub fn render_one(report: &StatusReport, format: OutputFormat) -> Result<String> {
+    match format {
+        OutputFormat::Json => Ok(serde_json::to_string_pretty(report)?),
+        OutputFormat::Yaml => Ok(serde_yaml::to_string(report)?),
+        OutputFormat::Table => {
+            let state = match report.status {
+                ServiceStatus::Running => "running",
+                ServiceStatus::Crashed => "crashed",
+                ServiceStatus::Exited => "exited",
+                ServiceStatus::Stopped | ServiceStatus::Stale => "not running",
+            };
+            let mut line = format!("Service '{}' is {}", report.formula, state);
+            if let Some(health) = report.health {
+                line += &format!(" and {}", format!("{:?}", health).to_lowercase());
+            }
+            if let Some(uptime) = report.uptime_secs {
+                line += &format!(" (up {})", format_duration(Duration::from_secs(uptime)));
+            }
+            line += ".";
+            if let Some(last_exit) = last_exit_summary(report) {
+                line += &format!(" Last exited {}.", last_exit);
+            }
+            Ok(line)
+        }
+    }
+}
+
+/// Renders every detail of a single service, history included.
+pub fn render_info(report: &StatusReport, format: OutputFormat) -> Result<String> {
+    if format != OutputFormat::Table {
+        return render_one(report, format);
+    }
+
+    let pids = report
+        .pids
+        .iter()
+        .map(|pid| pid.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let optional = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+    let mut fields = vec![
+        ("Service", report.formula.clone()),
+        ("Label", report.label.clone()),
+        ("Source", report.source.clone()),
+        ("Plist", report.plist_path.clone()),
+        ("Status", format!("{:?}", report.status)),
+        (
+            "Health",
+            optional(report.health.map(|health| format!("{:?}", health))),
+        ),
+        (
+            "PIDs",
+            if pids.is_empty() {
+                "-".to_string()
+            } else {
+                pids
+            },
+        ),
+        (
+            "Uptime",
+            optional(
+                report
+                    .uptime_secs
+                    .map(|uptime| format_duration(Duration::from_secs(uptime))),
+            ),
+        ),
+        (
+            "Started by",
+            optional(report.started_by.map(|by| by.to_string())),
+        ),
+        ("Restarts", report.restarts.to_string()),
+        ("Last exit", optional(last_exit_summary(report))),
+        ("Stdout", report.stdout_path.clone()),
+        ("Stderr", report.stderr_path.clone()),
+    ];
+    if report.requires_root {
+        fields.push(("Requires root", "yes".to_string()));
+    }
+
+    let mut out = fields
+        .iter()
+        .map(|(name, value)| format!("{:<14}{}", format!("{}:", name), value))
+        .collect::<Vec<_>>()
+        .join("\n");
+    if !report.history.is_empty() {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        out += "\nHistory:";
+        for entry in &report.history {
+            let ago = format_duration(Duration::from_secs(now.saturating_sub(entry.at)));
+            out += &format!("\n  {:>8} ago  {}", ago, describe_event(&entry.event));
+        }
+    }
+    Ok(out)
+}
+
+/// How the last run ended, e.g. `3m 5s ago with signal 9`.
+pub fn last_exit_summary(report: &StatusReport) -> Option<String> {
+    let ago = format_duration(Duration::from_secs(report.last_exit_secs_ago?));
+    let outcome = match (report.last_exit_signal, report.last_exit_code) {
+        (Some(signal), _) => format!(" with signal {}", signal),
+        (None, Some(code)) => format!(" with code {}", code),
+        (None, None) => String::new(),
+    };
+    Some(format!("{} ago{}", ago, outcome))
+}
+
+fn describe_event(event: &LifecycleEvent) -> String {
+    match event {
+        LifecycleEvent::Started { pid, by } => format!("started as PID {} by {}", pid, by),
+        LifecycleEvent::Restarted { pid } => format!("restarted as PID {}", pid),
+        LifecycleEvent::Adopted { pids } => format!("adopted PIDs {:?}", pids),
+        LifecycleEvent::Exited {
+            signal: Some(signal),
+            ..
+        } => format!("exited with signal {}", signal),
+        LifecycleEvent::Exited {
+            code: Some(code), ..
+        } => format!("exited with code {}", code),
+        LifecycleEvent::Exited { .. } => "exited".to_string(),
+        LifecycleEvent::Vanished => "found gone".to_string(),
+        LifecycleEvent::Stopped => "stopped".to_string(),
+    }
+}
+
+/// Renders the effective configuration of a service; the table shows where
+/// each value comes from.
+pub fn render_settings(settings: &ServiceSettings, format: OutputFormat) -> Result<String> {
+    match format {
+        OutputFormat::Json => return Ok(serde_json::to_string_pretty(settings)?),
+        OutputFormat::Yaml => return Ok(serde_yaml::to_string(settings)?),
+        OutputFormat::Table => {}
+    }
+
+    fn row<T>(name: &str, setting: &Setting<T>, value: String) -> (String, String, String) {
+        (name.to_string(), value, setting.origin.to_string())
+    }
+    let args = if settings.args.value.is_empty() {
+        "-".to_string()
+    } else {
+        settings.args.value.join(" ")
+    };
+    let mut rows = vec![
+        row("program", &settings.program, settings.program.value.clone()),
+        row("args", &settings.args, args),
+        row(
+            "working_directory",
+            &settings.working_directory,
+            settings.working_directory.value.clone(),
+        ),
+        row(
+            "stdout_path",
+            &settings.stdout_path,
+            settings.stdout_path.value.clone(),
+        ),
+        row(
+            "stderr_path",
+            &settings.stderr_path,
+            settings.stderr_path.value.clone(),
+        ),
+        row(
+            "keep_alive",
+            &settings.keep_alive,
+            describe_keep_alive(&settings.keep_alive.value),
+        ),
+        row(
+            "throttle_interval",
+            &settings.throttle_interval,
+            format!("{}s", settings.throttle_interval.value),
+        ),
+        row(
+            "stop_signal",
+            &settings.stop_signal,
+            settings.stop_signal.value.clone(),
+        ),
+        row(
+            "exit_timeout",
+            &settings.exit_timeout,
+            format!("{}s", settings.exit_timeout.value),
+        ),
+    ];
+    for (name, setting) in [("requires", &settings.requires), ("after", &settings.after)] {
+        let names = if setting.value.is_empty() {
+            "-".to_string()
+        } else {
+            setting.value.join(", ")
+        };
+        rows.push(row(name, setting, names));
+    }
+    rows.push(row(
+        "health_check",
+        &settings.health_check,
+        settings
+            .health_check
+            .value
+            .as_ref()
+            .map_or_else(|| "-".to_string(), describe_health_check),
+    ));
+    for (key, setting) in &settings.environment_variables {
+        rows.push(row(&format!("env.{}", key), setting, setting.value.clone()));
+    }
+
+    let name_width = rows.iter().map(|(name, ..)| name.len()).max().unwrap_or(0);
+    let value_width = rows
+        .iter()
+        .map(|(_, value, _)| value.len())
+        .max()
+        .unwrap_or(0);
+    Ok(rows
+        .iter()
+        .map(|(name, value, origin)| {
+            format!(
+                "{:<name_width$}  {:<value_width$}  ({})",
+                name, value, origin
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+fn describe_health_check(check: &HealthCheck) -> String {
+    format!(
+        "{}, every {}s, {} retries",
+        check.describe(),
+        check.interval().as_secs(),
+        check.retries()
+    )
+}
+
+fn describe_keep_alive(keep_alive: &KeepAlive) -> String {
+    let conditions = match keep_alive {
+        KeepAlive::Always(always) => return always.to_string(),
+        KeepAlive::Conditions(conditions) => conditions,
+    };
+    let mut parts = Vec::new();
+    if let Some(wanted) = conditions.successful_exit {
+        parts.push(format!("successful_exit={}", wanted));
+    }
+    if let Some(wanted) = conditions.crashed {
+        parts.push(format!("crashed={}", wanted));
+    }
+    if let Some(wanted) = conditions.network_state {
+        parts.push(format!("network_state={}", wanted));
+    }
+    for (path, wanted) in &conditions.path_state {
+        parts.push(format!("path_state[{}]={}", path, wanted));
+    }
+    for (label, wanted) in &conditions.other_job_enabled {
+        parts.push(format!("other_job_enabled[{}]={}", label, wanted));
+    }
+    parts.join(", ")
+}
+
+/// Renders a list of reports; the table groups them by source, in order.
+pub fn render_all(reports: &[StatusReport], format: OutputFormat) -> Result<String> {
+    match format {
+        OutputFormat::Json => Ok(serde_json::to_string_pretty(reports)?),
+        OutputFormat::Yaml => Ok(serde_yaml::to_string(reports)?),
+        OutputFormat::Table => {
+            let mut out = String::new();
+            let mut current_source: Option<&str> = None;
+            for report in reports {
+                if current_source != Some(report.source.as_str()) {
+                    current_source = Some(&report.source);
+                    out += &format!("{:-<30}\n{}\n{:-<30}\n", "", report.source, "");
+                }
+                out += &format!("{}\n", table_row(report));
+            }
+            Ok(out.trim_end().to_string())
+        }
+    }
+}
+
+fn table_row(report: &StatusReport) -> String {
+    let pids = report
+        .pids
+        .iter()
+        .map(|pid| pid.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    let uptime = report
+        .uptime_secs
+        .map(|uptime| format_duration(Duration::from_secs(uptime)))
+        .unwrap_or_default();
+    let health = report
+        .health
+        .map(|health| format!("{:?}", health))
+        .unwrap_or_default();
+    format!(
+        "{:<20} {:<8} {:<9} {:<10} {}",
+        report.formula,
+        format!("{:?}", report.status),
+        health,
+        pids,
+        uptime
+    )
+    .trim_end()
+    .to_string()
+}
+
+/// Formats a duration with its two most significant units, e.g. `3h 12m`.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (days, hours, minutes, seconds) = (
+        secs / 86_400,
+        secs % 86_400 / 3_600,
+        secs % 3_600 / 60,
+        secs % 60,
+    );
+    match (days, hours, minutes) {
+        (0, 0, 0) => format!("{}s", seconds),
+        (0, 0, _) => format!("{}m {}s", minutes, seconds),
+        (0, _, _) => format!("{}h {}m", hours, minutes),
+        _ => format!("{}d {}h", days, hours),
+    }
+}
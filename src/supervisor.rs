@@ -0,0 +1,317 @@
+use std::io::Write;
+use std::os::unix::process::ExitStatusExt;
+use std::path::Path;
+use std::process::{ExitStatus, Stdio};
+use std::time::Duration;
+
+use nix::libc::SIGKILL;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::{Child, Command};
+use tokio::sync::watch;
+use tokio::task::{JoinHandle, JoinSet};
+use tokio::time::{Instant, sleep_until, timeout};
+
+use crate::error::Result;
+use crate::global_config::GlobalConfig;
+use crate::launchd_config::KeepAlive;
+use crate::service_manager::ServiceManager;
+use crate::services::service_log::{LineFormatter, LogWriter};
+use crate::services::service_process::SignalTarget;
+use crate::services::service_settings::Overrides;
+use crate::services::service_state::{ServiceStatus, StartedBy};
+
+/// How long to wait for a finished run's output to reach its logs.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Long-running owner of service processes, restarting them per `KeepAlive`.
+pub struct Supervisor {
+    managers: Vec<ServiceManager>,
+}
+
+impl Supervisor {
+    /// Resolves every service up front; with `by_label`, names are exact launchd labels.
+    ///
+    /// `overrides` come from the command line and apply to every service.
+    pub fn new(
+        formulas: Vec<String>,
+        source: Option<&str>,
+        by_label: bool,
+        global: &GlobalConfig,
+        overrides: &Overrides,
+    ) -> Result<Self> {
+        let managers = formulas
+            .into_iter()
+            .map(|formula| {
+                let manager = if by_label {
+                    ServiceManager::with_label(formula, source, global)
+                } else {
+                    ServiceManager::with_source(formula, source, global)
+                };
+                manager?.with_overrides(overrides)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        for manager in &managers {
+            manager.ensure_privileges()?;
+        }
+        Ok(Supervisor { managers })
+    }
+
+    /// Runs until every service has exited for good or flint receives SIGINT/SIGTERM.
+    pub async fn run(self) -> Result<()> {
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        tokio::spawn(async move {
+            wait_for_shutdown_signal().await;
+            let _ = shutdown_tx.send(true);
+        });
+
+        let mut tasks = JoinSet::new();
+        for manager in self.managers {
+            let state = manager.service_state().read_state()?;
+            if state.status() == &ServiceStatus::Running {
+                println!("Service '{}' is already running.", manager.formula());
+                continue;
+            }
+            tasks.spawn(supervise(manager, shutdown_rx.clone()));
+        }
+
+        while let Some(result) = tasks.join_next().await {
+            if let Ok(Err(e)) = result {
+                eprintln!("❌ Error: {}", e);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Decides whether a job must be launched again after exiting with `status`,
+/// following the launchd `KeepAlive` semantics.
+///
+/// `other_job_running` reports whether the job with the given label is running,
+/// for the `OtherJobEnabled` condition.
+pub fn should_restart(
+    keep_alive: Option<&KeepAlive>,
+    status: &ExitStatus,
+    other_job_running: impl Fn(&str) -> bool,
+) -> bool {
+    let conditions = match keep_alive {
+        None => return false,
+        Some(KeepAlive::Always(keep_alive)) => return *keep_alive,
+        Some(KeepAlive::Conditions(conditions)) => conditions,
+    };
+
+    let crashed = status.signal().is_some();
+    conditions
+        .successful_exit
+        .is_some_and(|wanted| status.success() == wanted)
+        || conditions.crashed.is_some_and(|wanted| crashed == wanted)
+        || conditions
+            .path_state
+            .iter()
+            .any(|(path, wanted)| Path::new(path).exists() == *wanted)
+        || conditions
+            .other_job_enabled
+            .iter()
+            .any(|(label, wanted)| other_job_running(label) == *wanted)
+}
+
+async fn supervise(manager: ServiceManager, mut shutdown: watch::Receiver<bool>) -> Result<()> {
+    let throttle = manager.settings().throttle_interval();
+    let mut restarting = false;
+
+    loop {
+        let spawned_at = Instant::now();
+        let (mut child, copiers) = spawn(&manager)?;
+        if let Some(pid) = child.id() {
+            if restarting {
+                manager.service_state().mark_restarted(pid as i32)?;
+            } else {
+                manager
+                    .service_state()
+                    .mark_spawned(pid as i32, StartedBy::Supervise)?;
+            }
+        }
+        println!("Service '{}' started", manager.formula());
+
+        let mut unhealthy = false;
+        let status = tokio::select! {
+            status = child.wait() => status?,
+            failures = failing_health_checks(&manager) => {
+                println!(
+                    "Service '{}' failed {} health checks in a row, restarting.",
+                    manager.formula(),
+                    failures
+                );
+                unhealthy = true;
+                terminate(&manager, &mut child).await?;
+                child.wait().await?
+            }
+            _ = shutdown.changed() => {
+                terminate(&manager, &mut child).await?;
+                drain(copiers).await;
+                manager.service_state().mark_stopped()?;
+                println!("Service '{}' stopped successfully.", manager.formula());
+                return Ok(());
+            }
+        };
+        drain(copiers).await;
+        manager
+            .service_state()
+            .mark_exited(status.code(), status.signal())?;
+
+        let keep_alive = &manager.settings().keep_alive.value;
+        let other_job_running = |label: &str| is_job_running(label, manager.global());
+        // A service killed for failing its health check restarts whatever its KeepAlive.
+        if !unhealthy {
+            if !should_restart(Some(keep_alive), &status, other_job_running) {
+                println!(
+                    "Service '{}' exited with {}, not restarting.",
+                    manager.formula(),
+                    status
+                );
+                return Ok(());
+            }
+            println!(
+                "Service '{}' exited with {}, restarting.",
+                manager.formula(),
+                status
+            );
+        }
+        tokio::select! {
+            _ = sleep_until(spawned_at + throttle) => {}
+            _ = shutdown.changed() => return Ok(()),
+        }
+        restarting = true;
+    }
+}
+
+/// Probes the service every health check interval and completes, with the number
+/// of failures, once `retries` probes in a row have failed.
+///
+/// Never completes for services without a health check.
+async fn failing_health_checks(manager: &ServiceManager) -> u32 {
+    let Some(check) = manager.settings().health_check.value.clone() else {
+        return std::future::pending().await;
+    };
+    let mut failures = 0;
+    loop {
+        tokio::time::sleep(check.interval()).await;
+        let probe = check.clone();
+        let result = tokio::task::spawn_blocking(move || probe.check())
+            .await
+            .unwrap_or_else(|err| Err(err.to_string()));
+        match result {
+            Ok(()) => failures = 0,
+            Err(reason) => {
+                failures += 1;
+                eprintln!(
+                    "Service '{}' failed its health check ({}/{}): {}",
+                    manager.formula(),
+                    failures,
+                    check.retries(),
+                    reason
+                );
+                if failures >= check.retries() {
+                    return failures;
+                }
+            }
+        }
+    }
+}
+
+/// Spawns the service with its output piped through flint, so logs can rotate
+/// while it runs.
+fn spawn(manager: &ServiceManager) -> Result<(Child, Vec<JoinHandle<()>>)> {
+    manager.log().create_log_dirs()?;
+    let stdout_log = manager.log().stdout_writer()?;
+    let stderr_log = manager.log().stderr_writer()?;
+
+    let mut command = Command::from(manager.command());
+    command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    let mut child = command.spawn()?;
+    let pid = child.id().unwrap_or_default();
+    let format = manager.log().format();
+
+    let mut copiers = Vec::new();
+    if let Some(stdout) = child.stdout.take() {
+        let lines = LineFormatter::new(format, "stdout", pid);
+        copiers.push(tokio::spawn(copy_to_log(stdout, stdout_log, lines)));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        let lines = LineFormatter::new(format, "stderr", pid);
+        copiers.push(tokio::spawn(copy_to_log(stderr, stderr_log, lines)));
+    }
+    Ok((child, copiers))
+}
+
+/// Copies a child's output into its log, formatting each line, until the pipe closes.
+async fn copy_to_log(
+    mut output: impl AsyncRead + Unpin,
+    mut log: LogWriter,
+    mut lines: LineFormatter,
+) {
+    let mut buffer = [0u8; 8192];
+    loop {
+        let (formatted, done) = match output.read(&mut buffer).await {
+            Ok(0) | Err(_) => (lines.finish(), true),
+            Ok(read) => (lines.feed(&buffer[..read]), false),
+        };
+        if let Err(e) = log.write_all(&formatted) {
+            eprintln!("Error writing to {}: {}", log.path().display(), e);
+            break;
+        }
+        if done {
+            break;
+        }
+    }
+    let _ = log.flush();
+}
+
+/// Waits for the output of a finished run to be written, without hanging on
+/// grandchildren that inherited the pipes.
+async fn drain(copiers: Vec<JoinHandle<()>>) {
+    for copier in copiers {
+        let _ = timeout(DRAIN_TIMEOUT, copier).await;
+    }
+}
+
+/// Sends the stop signal to the child's process group and waits up to the exit
+/// timeout before resorting to SIGKILL.
+async fn terminate(manager: &ServiceManager, child: &mut Child) -> Result<()> {
+    let Some(pid) = child.id() else {
+        return Ok(());
+    };
+    let group = SignalTarget::Group(pid as i32);
+    group.signal(manager.settings().stop_signal_number())?;
+
+    let exit_timeout = manager.settings().exit_timeout();
+    if timeout(exit_timeout, child.wait()).await.is_err() {
+        group.signal(SIGKILL)?;
+        child.wait().await?;
+    }
+    Ok(())
+}
+
+fn is_job_running(label: &str, global: &GlobalConfig) -> bool {
+    ServiceManager::with_label(label.to_string(), None, global)
+        .and_then(|manager| manager.service_state().read_state())
+        .is_ok_and(|state| state.status() == &ServiceStatus::Running)
+}
+
+pub(crate) async fn wait_for_shutdown_signal() {
+    let mut sigterm = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+    {
+        Ok(sigterm) => sigterm,
+        Err(_) => {
+            let _ = tokio::signal::ctrl_c().await;
+            return;
+        }
+    };
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = sigterm.recv() => {}
+    }
+}
@@ -0,0 +1,110 @@
+use flint::deps::{DependencyGraph, GraphFormat};
+use flint::error::Error;
+use flint::global_config::GlobalConfig;
+use flint::project::Project;
+use std::fs;
+use std::sync::{Mutex, MutexGuard};
+use tempfile::TempDir;
+
+static HOME_LOCK: Mutex<()> = Mutex::new(());
+
+const SHOP: &str = r#"
+name = "shop"
+
+[services.web]
+program = "/bin/true"
+requires = ["db"]
+after = ["worker", "mail"]
+
+[services.worker]
+program = "/bin/true"
+requires = ["db", "cache"]
+
+[services.db]
+program = "/bin/true"
+
+[services.cache]
+program = "/bin/true"
+"#;
+
+/// Resolves the graph of `names` in a project defined by `flint.toml`.
+fn graph(flint_toml: &str, names: &[&str]) -> (MutexGuard<'static, ()>, TempDir, DependencyGraph) {
+    let guard = HOME_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let tmp = TempDir::new().unwrap();
+    unsafe {
+        std::env::set_var("HOME", tmp.path());
+    }
+    fs::write(tmp.path().join("flint.toml"), flint_toml).unwrap();
+    let project = Project::load(&tmp.path().join("flint.toml")).unwrap();
+    let names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+    let managers = project.managers(&names, &GlobalConfig::default()).unwrap();
+    let graph = DependencyGraph::resolve(managers).unwrap();
+    (guard, tmp, graph)
+}
+
+fn formulas<'a>(
+    managers: impl IntoIterator<Item = &'a flint::service_manager::ServiceManager>,
+) -> Vec<String> {
+    managers
+        .into_iter()
+        .map(|manager| manager.formula().to_string())
+        .collect()
+}
+
+#[test]
+fn test_requires_pulls_in_services_and_orders_them() {
+    let (_guard, _tmp, graph) = graph(SHOP, &["web"]);
+
+    // worker is only `after`, so it is not pulled in.
+    assert_eq!(formulas(graph.managers()), ["shop.web", "shop.db"]);
+    assert_eq!(
+        formulas(graph.start_order().unwrap()),
+        ["shop.db", "shop.web"]
+    );
+    assert_eq!(formulas(graph.stop_order().unwrap()), ["shop.web"]);
+}
+
+#[test]
+fn test_after_orders_services_started_together() {
+    let (_guard, _tmp, graph) = graph(SHOP, &[]);
+
+    assert_eq!(
+        formulas(graph.start_order().unwrap()),
+        ["shop.cache", "shop.db", "shop.worker", "shop.web"]
+    );
+    assert_eq!(
+        formulas(graph.stop_order().unwrap()),
+        ["shop.web", "shop.worker", "shop.db", "shop.cache"]
+    );
+}
+
+#[test]
+fn test_cycles_are_reported() {
+    let cyclic = SHOP.replace(
+        "[services.db]\nprogram = \"/bin/true\"\n",
+        "[services.db]\nprogram = \"/bin/true\"\nafter = [\"web\"]\n",
+    );
+    let (_guard, _tmp, graph) = graph(&cyclic, &[]);
+
+    match graph.start_order() {
+        Err(Error::DependencyCycle { cycle }) => {
+            assert_eq!(cycle, ["shop.db", "shop.web", "shop.db"]);
+        }
+        other => panic!("unexpected {:?}", other.map(formulas)),
+    }
+    assert!(graph.render(GraphFormat::Tree).contains("(cycle)"));
+}
+
+#[test]
+fn test_render_tree_and_dot() {
+    let (_guard, _tmp, graph) = graph(SHOP, &["worker"]);
+
+    assert_eq!(
+        graph.render(GraphFormat::Tree),
+        "shop.worker\n├── requires shop.db\n└── requires shop.cache"
+    );
+    let dot = graph.render(GraphFormat::Dot);
+    assert!(dot.starts_with("digraph flint {\n"));
+    assert!(dot.contains("    \"shop.worker\" -> \"shop.cache\" [label=\"requires\"];\n"));
+    assert!(dot.ends_with('}'));
+}
@@ -0,0 +1,96 @@
+use flint::error::Error;
+use flint::global_config::GlobalConfig;
+use flint::launchd_config::LaunchdConfigBuilder;
+use flint::services::service_settings::{Origin, ServiceSettings};
+use flint::status_report::OutputFormat;
+use std::path::Path;
+use std::time::Duration;
+
+fn parse_error(text: &str) -> (usize, String, String) {
+    match GlobalConfig::parse(text, Path::new("config.toml")) {
+        Err(Error::InvalidConfig {
+            line, key, message, ..
+        }) => (line, key, message),
+        other => panic!("expected an invalid config, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_parse_global_config() {
+    let config = GlobalConfig::parse(
+        r#"
+homebrew_prefix = "/opt/homebrew"
+state_dir = "/var/flint"
+log_dir = "/var/log/flint"
+stop_timeout = 5
+output_format = "json"
+
+[services]
+stop_signal = "INT"
+environment_variables = { RUST_LOG = "info" }
+"#,
+        Path::new("config.toml"),
+    )
+    .unwrap();
+
+    assert_eq!(config.homebrew_prefix(), Some(Path::new("/opt/homebrew")));
+    assert_eq!(config.state_dir(), Some(Path::new("/var/flint")));
+    assert_eq!(config.stop_timeout(), Some(Duration::from_secs(5)));
+    assert_eq!(config.output_format(), Some(OutputFormat::Json));
+    assert_eq!(config.services().stop_signal.as_deref(), Some("INT"));
+}
+
+#[test]
+fn test_errors_point_to_key_and_line() {
+    let (line, key, message) = parse_error("stop_timeout = 5\nlog_dirr = \"/tmp\"\n");
+    assert_eq!((line, key.as_str()), (2, "log_dirr"));
+    assert!(message.contains("unknown field"), "{}", message);
+
+    let (line, key, _) = parse_error("\n\nstop_timeout = \"soon\"\n");
+    assert_eq!((line, key.as_str()), (3, "stop_timeout"));
+
+    let (line, key, _) = parse_error("output_format = \"xml\"\n");
+    assert_eq!((line, key.as_str()), (1, "output_format"));
+
+    let (line, key, _) = parse_error("stop_timeout = 3\nstate_dir = \"relative/dir\"\n");
+    assert_eq!((line, key.as_str()), (2, "state_dir"));
+
+    let (line, key, message) =
+        parse_error("stop_signal = \"KILL\"\n[services]\nstop_signal = \"NOPE\"\n");
+    assert!(message.contains("unknown field"), "{}", message);
+    assert_eq!((line, key.as_str()), (1, "stop_signal"));
+    let (line, key, _) = parse_error("[services]\n\nstop_signal = \"NOPE\"\n");
+    assert_eq!((line, key.as_str()), (3, "stop_signal"));
+}
+
+#[test]
+fn test_global_layer_sits_between_plist_and_defaults() {
+    let config = GlobalConfig::parse(
+        "log_dir = \"/var/log/flint\"\nstop_timeout = 7\n[services]\nthrottle_interval = 2\n",
+        Path::new("config.toml"),
+    )
+    .unwrap();
+    let launchd = LaunchdConfigBuilder::default()
+        .program(Some("/bin/true".to_string()))
+        .args(Vec::new())
+        .working_directory("/".to_string())
+        .stdout_path(Some("/tmp/app.log".to_string()))
+        .stderr_path(None)
+        .throttle_interval(Some(30))
+        .build()
+        .unwrap();
+
+    let settings = ServiceSettings::resolve(
+        "app",
+        &launchd,
+        &config,
+        &[(Origin::Global, config.services())],
+    )
+    .unwrap();
+    assert_eq!(settings.stdout_path.value, "/tmp/app.log");
+    assert_eq!(settings.stderr_path.value, "/var/log/flint/app_error.log");
+    assert_eq!(settings.stderr_path.origin, Origin::Global);
+    assert_eq!(settings.exit_timeout.value, 7);
+    assert_eq!(settings.throttle_interval.value, 2);
+    assert_eq!(settings.throttle_interval.origin, Origin::Global);
+}
@@ -0,0 +1,172 @@
+use flint::launchd_config::{CalendarInterval, KeepAlive, LaunchdConfig};
+
+const FULL_PLIST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>homebrew.mxcl.postgresql@16</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>/opt/homebrew/opt/postgresql@16/bin/postgres</string>
+        <string>-D</string>
+        <string>/opt/homebrew/var/postgresql@16</string>
+    </array>
+    <key>EnvironmentVariables</key>
+    <dict>
+        <key>LC_ALL</key>
+        <string>en_US.UTF-8</string>
+    </dict>
+    <key>KeepAlive</key>
+    <dict>
+        <key>SuccessfulExit</key>
+        <false/>
+        <key>PathState</key>
+        <dict>
+            <key>/tmp/pg.enabled</key>
+            <true/>
+        </dict>
+    </dict>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>StartCalendarInterval</key>
+    <array>
+        <dict>
+            <key>Hour</key>
+            <integer>3</integer>
+        </dict>
+        <dict>
+            <key>Weekday</key>
+            <integer>0</integer>
+            <key>Minute</key>
+            <integer>30</integer>
+        </dict>
+    </array>
+    <key>WatchPaths</key>
+    <array>
+        <string>/etc/hosts</string>
+    </array>
+    <key>Umask</key>
+    <integer>18</integer>
+    <key>Nice</key>
+    <integer>-5</integer>
+    <key>ThrottleInterval</key>
+    <integer>30</integer>
+    <key>ExitTimeOut</key>
+    <integer>5</integer>
+    <key>SoftResourceLimits</key>
+    <dict>
+        <key>NumberOfFiles</key>
+        <integer>4096</integer>
+    </dict>
+    <key>StandardInPath</key>
+    <string>/dev/null</string>
+    <key>WorkingDirectory</key>
+    <string>/opt/homebrew</string>
+    <key>LimitLoadToSessionType</key>
+    <string>Aqua</string>
+</dict>
+</plist>
+"#;
+
+fn parse(xml: &str) -> LaunchdConfig {
+    LaunchdConfig::from_bytes(xml.as_bytes()).unwrap()
+}
+
+#[test]
+fn test_parse_full_plist() {
+    let config = parse(FULL_PLIST);
+
+    assert_eq!(config.label(), Some("homebrew.mxcl.postgresql@16"));
+    assert_eq!(
+        config.program(),
+        "/opt/homebrew/opt/postgresql@16/bin/postgres"
+    );
+    assert_eq!(config.args(), ["-D", "/opt/homebrew/var/postgresql@16"]);
+    assert_eq!(
+        config
+            .environment_variables()
+            .get("LC_ALL")
+            .map(String::as_str),
+        Some("en_US.UTF-8")
+    );
+    assert!(config.run_at_load());
+    assert_eq!(config.watch_paths(), ["/etc/hosts"]);
+    assert_eq!(config.umask(), Some(18));
+    assert_eq!(config.nice(), Some(-5));
+    assert_eq!(config.throttle_interval(), Some(30));
+    assert_eq!(config.exit_timeout(), Some(5));
+    assert_eq!(config.stdin_path(), Some("/dev/null"));
+    assert_eq!(config.working_directory(), "/opt/homebrew");
+    assert_eq!(
+        config
+            .soft_resource_limits()
+            .and_then(|l| l.number_of_files),
+        Some(4096)
+    );
+    assert_eq!(
+        config.start_calendar_intervals(),
+        [
+            CalendarInterval {
+                hour: Some(3),
+                ..Default::default()
+            },
+            CalendarInterval {
+                minute: Some(30),
+                weekday: Some(0),
+                ..Default::default()
+            },
+        ]
+    );
+
+    match config.keep_alive() {
+        Some(KeepAlive::Conditions(conditions)) => {
+            assert_eq!(conditions.successful_exit, Some(false));
+            assert_eq!(conditions.path_state.get("/tmp/pg.enabled"), Some(&true));
+        }
+        other => panic!("unexpected KeepAlive: {:?}", other),
+    }
+}
+
+#[test]
+fn test_unknown_keys_are_preserved() {
+    let config = parse(FULL_PLIST);
+
+    assert_eq!(
+        config
+            .extra()
+            .get("LimitLoadToSessionType")
+            .and_then(|v| v.as_string()),
+        Some("Aqua")
+    );
+    assert!(!config.extra().contains_key("Label"));
+}
+
+#[test]
+fn test_parse_minimal_plist_with_bool_keep_alive() {
+    let config = parse(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0">
+<dict>
+    <key>Program</key>
+    <string>/usr/bin/true</string>
+    <key>KeepAlive</key>
+    <true/>
+    <key>StartCalendarInterval</key>
+    <dict>
+        <key>Minute</key>
+        <integer>15</integer>
+    </dict>
+</dict>
+</plist>
+"#,
+    );
+
+    assert_eq!(config.program(), "/usr/bin/true");
+    assert!(config.args().is_empty());
+    assert_eq!(config.working_directory(), ".");
+    assert_eq!(config.keep_alive(), Some(&KeepAlive::Always(true)));
+    assert_eq!(config.start_calendar_intervals().len(), 1);
+    assert!(config.environment_variables().is_empty());
+    assert!(config.extra().is_empty());
+}
@@ -0,0 +1,76 @@
+use flint::logs::{Follower, parse_since, tail};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::time::{Duration, SystemTime};
+use tempfile::TempDir;
+
+fn append(path: &std::path::Path, text: &str) {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .unwrap();
+    file.write_all(text.as_bytes()).unwrap();
+}
+
+#[test]
+fn test_parse_since() {
+    let ago = SystemTime::now()
+        .duration_since(parse_since("15m").unwrap())
+        .unwrap();
+    assert!(ago.as_secs().abs_diff(15 * 60) <= 5, "{:?}", ago);
+
+    assert_eq!(
+        parse_since("2024-05-01T12:00:00Z").unwrap(),
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1_714_564_800)
+    );
+    assert_eq!(
+        parse_since("1714564800").unwrap(),
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1_714_564_800)
+    );
+    assert!(parse_since("yesterday").is_err());
+    assert!(parse_since("5w").is_err());
+}
+
+#[test]
+fn test_tail_returns_last_lines() {
+    let tmp = TempDir::new().unwrap();
+    let path = tmp.path().join("app.log");
+    assert!(tail(&path, 3).unwrap().is_empty());
+
+    fs::write(&path, "1\n2\n3\n4\n5\n").unwrap();
+    assert_eq!(tail(&path, 3).unwrap(), ["3", "4", "5"]);
+    assert_eq!(tail(&path, 10).unwrap().len(), 5);
+}
+
+#[test]
+fn test_follower_survives_rotation() {
+    let tmp = TempDir::new().unwrap();
+    let path = tmp.path().join("app.log");
+    append(&path, "old\n");
+
+    let mut follower = Follower::at_end(&path);
+    assert!(follower.poll().unwrap().is_empty());
+
+    append(&path, "one\ntw");
+    assert_eq!(follower.poll().unwrap(), ["one"]);
+    append(&path, "o\nlast before rotation\n");
+    fs::rename(&path, tmp.path().join("app.log.1")).unwrap();
+    append(&path, "after rotation\n");
+
+    assert_eq!(
+        follower.poll().unwrap(),
+        ["two", "last before rotation", "after rotation"]
+    );
+}
+
+#[test]
+fn test_follower_waits_for_missing_log() {
+    let tmp = TempDir::new().unwrap();
+    let path = tmp.path().join("later.log");
+
+    let mut follower = Follower::at_end(&path);
+    assert!(follower.poll().unwrap().is_empty());
+    append(&path, "hello\n");
+    assert_eq!(follower.poll().unwrap(), ["hello"]);
+}
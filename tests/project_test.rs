@@ -0,0 +1,101 @@
+use flint::error::Error;
+use flint::global_config::GlobalConfig;
+use flint::project::Project;
+use flint::services::service_source::{ProjectSource, ServiceSource};
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn test_procfile_is_found_from_a_subdirectory() {
+    let tmp = TempDir::new().unwrap();
+    let root = tmp.path().join("My Shop");
+    fs::create_dir_all(root.join("app").join("models")).unwrap();
+    fs::write(
+        root.join("Procfile"),
+        "# services\nweb: bundle exec rails s\n\nworker: bundle exec sidekiq\n",
+    )
+    .unwrap();
+
+    let project = Project::find(&root.join("app").join("models"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(project.name(), "my-shop");
+    assert_eq!(project.root(), root.canonicalize().unwrap());
+    assert_eq!(
+        project.service_names().collect::<Vec<_>>(),
+        ["web", "worker"]
+    );
+
+    let source = ProjectSource::new(project);
+    assert_eq!(
+        source.discover().unwrap(),
+        ["my-shop.web", "my-shop.worker"]
+    );
+    let worker = source
+        .resolve_label("flint.my-shop.worker")
+        .unwrap()
+        .unwrap();
+    assert_eq!(worker.formula(), "my-shop.worker");
+    assert!(source.resolve("web").unwrap().is_none());
+}
+
+#[test]
+fn test_flint_toml_takes_precedence_and_runs_from_the_root() {
+    let tmp = TempDir::new().unwrap();
+    unsafe {
+        std::env::set_var("HOME", tmp.path());
+    }
+    let root = tmp.path().join("repo");
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join("Procfile"), "web: ./server\n").unwrap();
+    fs::write(
+        root.join("flint.toml"),
+        "name = \"acme\"\n\n[services.api]\nprogram = \"/bin/sleep\"\nargs = [\"60\"]\n",
+    )
+    .unwrap();
+
+    let project = Project::find(&root).unwrap().unwrap();
+    assert_eq!(project.file(), root.join("flint.toml"));
+
+    let managers = project.managers(&[], &GlobalConfig::default()).unwrap();
+    assert_eq!(managers.len(), 1);
+    let api = &managers[0];
+    assert_eq!(api.formula(), "acme.api");
+    assert_eq!(api.service().label(), "flint.acme.api");
+    assert_eq!(api.service().source(), "Project");
+    assert_eq!(api.settings().args.value, ["60"]);
+    assert_eq!(
+        api.settings().working_directory.value,
+        root.canonicalize().unwrap().display().to_string()
+    );
+
+    assert!(matches!(
+        project.managers(&["web".to_string()], &GlobalConfig::default()),
+        Err(Error::FormulaNotFound { .. })
+    ));
+}
+
+#[test]
+fn test_procfile_errors_point_to_the_line() {
+    let tmp = TempDir::new().unwrap();
+    let procfile = tmp.path().join("Procfile");
+
+    for (text, expected_line, expected_key) in [
+        ("web: ./server\nbroken line\n", 2, "broken line"),
+        ("web: ./server\n\nweb: ./other\n", 3, "web"),
+        ("web:\n", 1, "web"),
+    ] {
+        fs::write(&procfile, text).unwrap();
+        match Project::load(&procfile) {
+            Err(Error::InvalidConfig { line, key, .. }) => {
+                assert_eq!(
+                    (line, key.as_str()),
+                    (expected_line, expected_key),
+                    "{}",
+                    text
+                );
+            }
+            other => panic!("unexpected {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -1 +1,26 @@
+pub(crate) mod service_config_test;
+pub(crate) mod service_health_test;
+pub(crate) mod service_log_test;
+pub(crate) mod service_native_test;
+pub(crate) mod service_process_test;
+pub(crate) mod service_settings_test;
+pub(crate) mod service_source_test;
 pub(crate) mod service_state_test;
+pub(crate) mod service_user_agent_test;
+
+use std::sync::{Mutex, MutexGuard};
+use tempfile::TempDir;
+
+static HOME_LOCK: Mutex<()> = Mutex::new(());
+
+/// Point `$HOME` at a fresh temporary directory.
+///
+/// Tests touching `$HOME` hold the returned guard so they do not race each other.
+pub(crate) fn fake_home() -> (MutexGuard<'static, ()>, TempDir) {
+    let guard = HOME_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let tmp = TempDir::new().unwrap();
+    unsafe {
+        std::env::set_var("HOME", tmp.path());
+    }
+    (guard, tmp)
+}
@@ -0,0 +1,119 @@
+use super::fake_home;
+use flint::launchd_config::LaunchdConfigBuilder;
+use flint::services::service_config::ServiceConfig;
+use flint::services::service_log::{LogFormat, ServiceLog};
+use std::fs;
+
+fn write_config(home: &std::path::Path, formula: &str, json: &str) {
+    let dir = home.join(".config").join("flint");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join(format!("{}.json", formula)), json).unwrap();
+}
+
+#[test]
+fn test_load_missing_config_is_empty() {
+    let (_guard, _home) = fake_home();
+
+    let config = ServiceConfig::load("absent").unwrap();
+    assert!(config.environment_variables().is_empty());
+    assert!(!config.clear_environment());
+    assert_eq!(config.standard_out_path(), None);
+}
+
+#[test]
+fn test_load_environment_overrides() {
+    let (_guard, home) = fake_home();
+    write_config(
+        home.path(),
+        "redis",
+        r#"{ "environment_variables": { "LC_ALL": "C" }, "clear_environment": true }"#,
+    );
+
+    let config = ServiceConfig::load("redis").unwrap();
+    assert_eq!(
+        config
+            .environment_variables()
+            .get("LC_ALL")
+            .map(String::as_str),
+        Some("C")
+    );
+    assert!(config.clear_environment());
+}
+
+#[test]
+fn test_env_only_config_keeps_plist_log_paths() {
+    let (_guard, home) = fake_home();
+    write_config(
+        home.path(),
+        "postgres",
+        r#"{ "environment_variables": { "PGDATA": "/tmp/pg" } }"#,
+    );
+
+    let launchd = LaunchdConfigBuilder::default()
+        .program(Some("/usr/bin/postgres".to_string()))
+        .args(vec![])
+        .working_directory(".".to_string())
+        .stdout_path(Some("/tmp/pg.log".to_string()))
+        .stderr_path(Some("/tmp/pg_error.log".to_string()))
+        .build()
+        .unwrap();
+    let config = ServiceConfig::load("postgres").unwrap();
+    let log = ServiceLog::new("postgres".to_string(), &launchd, &config).unwrap();
+
+    assert_eq!(log.stdout_path(), "/tmp/pg.log");
+    assert_eq!(log.stderr_path(), "/tmp/pg_error.log");
+}
+
+#[test]
+fn test_load_log_rotation() {
+    let (_guard, home) = fake_home();
+    write_config(
+        home.path(),
+        "rotated",
+        r#"{ "log_rotation": { "max_size": 1048576, "daily": true, "compress": true } }"#,
+    );
+
+    let rotation = ServiceConfig::load("rotated")
+        .unwrap()
+        .log_rotation()
+        .clone();
+    assert_eq!(rotation.max_size, Some(1_048_576));
+    assert_eq!(rotation.max_files, 5);
+    assert!(rotation.daily);
+    assert!(rotation.compress);
+}
+
+#[test]
+fn test_load_log_format() {
+    let (_guard, home) = fake_home();
+    write_config(home.path(), "formatted", r#"{ "log_format": "json" }"#);
+
+    let config = ServiceConfig::load("formatted").unwrap();
+    assert_eq!(config.log_format(), LogFormat::Json);
+    assert_eq!(ServiceConfig::default().log_format(), LogFormat::Raw);
+}
+
+#[test]
+fn test_one_log_path_keeps_the_other_from_plist() {
+    let (_guard, home) = fake_home();
+    write_config(
+        home.path(),
+        "postgres",
+        r#"{ "standard_out_path": "/srv/pg.log", "stop_signal": "QUIT" }"#,
+    );
+
+    let launchd = LaunchdConfigBuilder::default()
+        .program(Some("/usr/bin/postgres".to_string()))
+        .args(vec![])
+        .working_directory(".".to_string())
+        .stdout_path(Some("/tmp/pg.log".to_string()))
+        .stderr_path(Some("/tmp/pg_error.log".to_string()))
+        .build()
+        .unwrap();
+    let config = ServiceConfig::load("postgres").unwrap();
+    let log = ServiceLog::new("postgres".to_string(), &launchd, &config).unwrap();
+
+    assert_eq!(log.stdout_path(), "/srv/pg.log");
+    assert_eq!(log.stderr_path(), "/tmp/pg_error.log");
+    assert_eq!(config.overrides().stop_signal.as_deref(), Some("QUIT"));
+}
@@ -0,0 +1,130 @@
+use flint::error::Error;
+use flint::services::service_health::HealthCheck;
+use flint::services::service_native::NativeDefinition;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread;
+
+/// Answers `count` HTTP requests on a local port with `status_line`.
+fn serve_http(status_line: &'static str, count: usize) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    thread::spawn(move || {
+        for stream in listener.incoming().take(count) {
+            let mut stream = stream.unwrap();
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request);
+            let _ = write!(stream, "{}\r\nContent-Length: 0\r\n\r\n", status_line);
+        }
+    });
+    address
+}
+
+#[test]
+fn test_validate_requires_exactly_one_probe() {
+    let none = HealthCheck::default();
+    assert!(matches!(
+        none.validate(),
+        Err(Error::InvalidHealthCheck { .. })
+    ));
+
+    let two = HealthCheck {
+        tcp: Some("127.0.0.1:5432".to_string()),
+        command: Some("true".to_string()),
+        ..Default::default()
+    };
+    assert!(matches!(
+        two.validate(),
+        Err(Error::InvalidHealthCheck { .. })
+    ));
+
+    let no_retries = HealthCheck {
+        tcp: Some("127.0.0.1:5432".to_string()),
+        retries: Some(0),
+        ..Default::default()
+    };
+    assert!(no_retries.validate().is_err());
+
+    let https = HealthCheck {
+        http: Some("https://localhost/".to_string()),
+        ..Default::default()
+    };
+    assert!(https.validate().is_err());
+}
+
+#[test]
+fn test_tcp_check_needs_a_listener() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let check = HealthCheck {
+        tcp: Some(listener.local_addr().unwrap().to_string()),
+        ..Default::default()
+    };
+    assert_eq!(check.check(), Ok(()));
+
+    drop(listener);
+    assert!(check.check().is_err());
+}
+
+#[test]
+fn test_http_check_accepts_success_status_only() {
+    let ok = HealthCheck {
+        http: Some(format!(
+            "http://{}/health",
+            serve_http("HTTP/1.0 200 OK", 1)
+        )),
+        ..Default::default()
+    };
+    assert_eq!(ok.check(), Ok(()));
+
+    let unavailable = HealthCheck {
+        http: Some(format!(
+            "http://{}/health",
+            serve_http("HTTP/1.0 503 Service Unavailable", 1)
+        )),
+        ..Default::default()
+    };
+    let reason = unavailable.check().unwrap_err();
+    assert!(reason.contains("503"), "{}", reason);
+}
+
+#[test]
+fn test_command_check_uses_exit_code() {
+    let passing = HealthCheck {
+        command: Some("exit 0".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(passing.check(), Ok(()));
+
+    let failing = HealthCheck {
+        command: Some("exit 3".to_string()),
+        ..Default::default()
+    };
+    assert!(failing.check().is_err());
+
+    let hanging = HealthCheck {
+        command: Some("sleep 5".to_string()),
+        timeout: Some(1),
+        ..Default::default()
+    };
+    let reason = hanging.check().unwrap_err();
+    assert!(reason.contains("timed out"), "{}", reason);
+}
+
+#[test]
+fn test_definition_reads_health_check_table() {
+    let definition: NativeDefinition = toml::from_str(
+        r#"
+program = "/usr/bin/postgres"
+
+[health_check]
+tcp = "127.0.0.1:5432"
+interval = 5
+"#,
+    )
+    .unwrap();
+    let check = definition.overrides().health_check.unwrap();
+    assert_eq!(check.tcp.as_deref(), Some("127.0.0.1:5432"));
+    assert_eq!(check.interval().as_secs(), 5);
+    assert_eq!(check.retries(), 3);
+    assert_eq!(check.describe(), "tcp 127.0.0.1:5432");
+}
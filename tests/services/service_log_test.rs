@@ -0,0 +1,134 @@
+use super::fake_home;
+use flint::launchd_config::LaunchdConfigBuilder;
+use flint::logs::parse_since;
+use flint::services::service_config::ServiceConfig;
+use flint::services::service_log::{
+    LineFormatter, LogFormat, LogRotation, LogWriter, ServiceLog, rotate, rotated_path,
+};
+use std::fs;
+use std::io::Write;
+use tempfile::TempDir;
+
+fn size_rotation(max_size: u64, max_files: usize) -> LogRotation {
+    LogRotation {
+        max_size: Some(max_size),
+        max_files,
+        ..LogRotation::default()
+    }
+}
+
+#[test]
+fn test_logs_are_appended_across_runs() {
+    let (_guard, home) = fake_home();
+    let stdout = home.path().join("logs").join("app.log");
+    let launchd = LaunchdConfigBuilder::default()
+        .program(Some("/bin/true".to_string()))
+        .args(Vec::new())
+        .stdout_path(Some(stdout.display().to_string()))
+        .stderr_path(None)
+        .working_directory("/".to_string())
+        .build()
+        .unwrap();
+    let log = ServiceLog::new("app".to_string(), &launchd, &ServiceConfig::default()).unwrap();
+
+    writeln!(log.open_stdout().unwrap(), "first run").unwrap();
+    writeln!(log.open_stdout().unwrap(), "second run").unwrap();
+    assert_eq!(
+        fs::read_to_string(&stdout).unwrap(),
+        "first run\nsecond run\n"
+    );
+}
+
+#[test]
+fn test_rotate_shifts_and_caps_files() {
+    let tmp = TempDir::new().unwrap();
+    let path = tmp.path().join("app.log");
+    let rotation = size_rotation(1, 2);
+
+    for run in ["one", "two", "three"] {
+        fs::write(&path, run).unwrap();
+        rotate(&path, &rotation).unwrap();
+    }
+
+    assert!(!path.exists());
+    assert_eq!(
+        fs::read_to_string(rotated_path(&path, 1, "")).unwrap(),
+        "three"
+    );
+    assert_eq!(
+        fs::read_to_string(rotated_path(&path, 2, "")).unwrap(),
+        "two"
+    );
+    assert!(!rotated_path(&path, 3, "").exists());
+}
+
+#[test]
+fn test_rotate_compresses() {
+    let tmp = TempDir::new().unwrap();
+    let path = tmp.path().join("app.log");
+    fs::write(&path, "old output").unwrap();
+
+    let rotation = LogRotation {
+        compress: true,
+        ..LogRotation::default()
+    };
+    rotate(&path, &rotation).unwrap();
+
+    assert!(rotated_path(&path, 1, ".gz").exists());
+    assert!(!rotated_path(&path, 1, "").exists());
+}
+
+#[test]
+fn test_writer_rotates_past_max_size() {
+    let tmp = TempDir::new().unwrap();
+    let path = tmp.path().join("app.log");
+    let mut writer = LogWriter::open(path.clone(), size_rotation(10, 3)).unwrap();
+
+    writer.write_all(b"12345678\n").unwrap();
+    writer.write_all(b"abcdefgh\n").unwrap();
+    writer.flush().unwrap();
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "abcdefgh\n");
+    assert_eq!(
+        fs::read_to_string(rotated_path(&path, 1, "")).unwrap(),
+        "12345678\n"
+    );
+}
+
+#[test]
+fn test_prefixed_lines_wait_for_newline() {
+    let mut lines = LineFormatter::new(LogFormat::Prefixed, "stderr", 42);
+
+    assert!(lines.feed(b"half a ").is_empty());
+    let formatted = String::from_utf8(lines.feed(b"line\nnext")).unwrap();
+    let (ts, rest) = formatted.split_once(' ').unwrap();
+    assert!(parse_since(ts).is_ok(), "{}", ts);
+    assert_eq!(rest, "stderr[42] half a line\n");
+
+    let last = String::from_utf8(lines.finish()).unwrap();
+    assert!(last.ends_with(" stderr[42] next\n"));
+    assert!(lines.finish().is_empty());
+}
+
+#[test]
+fn test_json_lines() {
+    let mut lines = LineFormatter::new(LogFormat::Json, "stdout", 7);
+    let formatted = String::from_utf8(lines.feed(b"say \"hi\"\r\nbye\n")).unwrap();
+
+    let records: Vec<serde_json::Value> = formatted
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["line"], "say \"hi\"");
+    assert_eq!(records[0]["stream"], "stdout");
+    assert_eq!(records[0]["pid"], 7);
+    assert!(records[1]["ts"].is_string());
+}
+
+#[test]
+fn test_raw_lines_pass_through() {
+    let mut lines = LineFormatter::new(LogFormat::Raw, "stdout", 7);
+    assert_eq!(lines.feed(b"no newline"), b"no newline");
+    assert!(lines.finish().is_empty());
+}
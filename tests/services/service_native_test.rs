@@ -0,0 +1,81 @@
+use flint::error::Error;
+use flint::launchd_config::KeepAlive;
+use flint::services::service_native::NativeDefinition;
+use flint::services::service_source::{NativeSource, ServiceSources};
+use std::fs;
+use tempfile::TempDir;
+
+const WEB: &str = r#"
+program = "/usr/bin/python3"
+args = ["-m", "http.server"]
+working_directory = "/srv/www"
+keep_alive = true
+requires = ["db"]
+
+[environment_variables]
+PORT = "8000"
+"#;
+
+#[test]
+fn test_definition_maps_onto_launchd_config() {
+    let tmp = TempDir::new().unwrap();
+    let path = tmp.path().join("web.toml");
+    fs::write(&path, WEB).unwrap();
+
+    let definition = NativeDefinition::load(&path).unwrap();
+    let launchd = definition.launchd_config("web").unwrap();
+    assert_eq!(launchd.label(), Some("flint.web"));
+    assert_eq!(launchd.program(), "/usr/bin/python3");
+    assert_eq!(launchd.args(), ["-m", "http.server"]);
+    assert_eq!(launchd.working_directory(), "/srv/www");
+    assert_eq!(launchd.environment_variables()["PORT"], "8000");
+    assert_eq!(launchd.keep_alive(), Some(&KeepAlive::Always(true)));
+    assert_eq!(
+        definition.overrides().requires,
+        Some(vec!["db".to_string()])
+    );
+}
+
+#[test]
+fn test_definition_errors_point_to_key() {
+    let tmp = TempDir::new().unwrap();
+    let path = tmp.path().join("web.toml");
+    fs::write(&path, "program = \"/bin/true\"\nrestart = true\n").unwrap();
+
+    match NativeDefinition::load(&path) {
+        Err(Error::InvalidConfig { line, key, .. }) => {
+            assert_eq!((line, key.as_str()), (2, "restart"));
+        }
+        other => panic!("unexpected {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn test_native_source_resolves_by_name_and_label() {
+    let tmp = TempDir::new().unwrap();
+    fs::write(tmp.path().join("web.toml"), WEB).unwrap();
+    fs::write(
+        tmp.path().join("worker.toml"),
+        "label = \"com.example.worker\"\nprogram = \"/bin/true\"\n",
+    )
+    .unwrap();
+    fs::write(tmp.path().join("notes.txt"), "ignored").unwrap();
+
+    let mut sources = ServiceSources::default();
+    sources.register(NativeSource::new(tmp.path().to_path_buf()));
+
+    let web = sources.resolve("web", None).unwrap();
+    assert_eq!(web.source(), "Flint");
+    assert_eq!(web.label(), "flint.web");
+
+    let worker = sources.resolve_label("com.example.worker", None).unwrap();
+    assert_eq!(worker.formula(), "worker");
+
+    let names: Vec<_> = sources
+        .discover(None)
+        .unwrap()
+        .iter()
+        .map(|service| service.formula().to_string())
+        .collect();
+    assert_eq!(names, ["web", "worker"]);
+}
@@ -0,0 +1,89 @@
+use flint::services::service_process::{
+    SignalTarget, is_alive, parse_signal, runs_program, signal_name, start_time, wait_for_exit,
+};
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::time::Duration;
+
+#[test]
+fn test_own_process_is_alive_with_stable_start_time() {
+    let pid = std::process::id() as i32;
+
+    assert!(is_alive(pid));
+    let first = start_time(pid);
+    assert!(first.is_some());
+    assert_eq!(first, start_time(pid));
+}
+
+#[test]
+fn test_exited_process_has_no_start_time() {
+    let mut child = std::process::Command::new("true").spawn().unwrap();
+    let pid = child.id() as i32;
+    child.wait().unwrap();
+
+    assert!(!is_alive(pid));
+    assert_eq!(start_time(pid), None);
+}
+
+#[test]
+fn test_invalid_pid_is_not_alive() {
+    assert!(!is_alive(0));
+    assert!(!is_alive(-1));
+}
+
+#[test]
+fn test_signal_group_reaches_forked_children() {
+    let mut child = std::process::Command::new("sh")
+        .args(["-c", "sleep 100 & sleep 100"])
+        .process_group(0)
+        .spawn()
+        .unwrap();
+    let group = SignalTarget::Group(child.id() as i32);
+    assert!(group.is_alive());
+
+    group.signal(nix::libc::SIGKILL).unwrap();
+    child.wait().unwrap();
+
+    // The forked `sleep` is reparented and reaped outside of our control.
+    assert!(wait_for_exit(&[group], Duration::from_secs(5)));
+}
+
+#[test]
+fn test_dangerous_targets_are_ignored() {
+    for target in [
+        SignalTarget::Process(0),
+        SignalTarget::Process(-1),
+        SignalTarget::Group(0),
+        SignalTarget::Group(1),
+    ] {
+        assert!(!target.is_alive());
+        assert!(target.signal(nix::libc::SIGTERM).is_ok());
+    }
+}
+
+#[test]
+fn test_parse_signal_accepts_names_and_numbers() {
+    assert_eq!(parse_signal("HUP"), Some(nix::libc::SIGHUP));
+    assert_eq!(parse_signal("sigusr1"), Some(nix::libc::SIGUSR1));
+    assert_eq!(parse_signal("SIGTERM"), Some(nix::libc::SIGTERM));
+    assert_eq!(parse_signal("9"), Some(nix::libc::SIGKILL));
+    assert_eq!(parse_signal("NOPE"), None);
+    assert_eq!(parse_signal("999"), None);
+    assert_eq!(signal_name(nix::libc::SIGHUP), "SIGHUP");
+}
+
+#[test]
+fn test_runs_program_checks_the_executable() {
+    let mut child = std::process::Command::new("/bin/sleep")
+        .arg("30")
+        .spawn()
+        .unwrap();
+    let pid = child.id() as i32;
+
+    assert_eq!(runs_program(pid, Path::new("/bin/sleep")), Some(true));
+    assert_eq!(runs_program(pid, Path::new("/bin/cat")), Some(false));
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert_eq!(runs_program(pid, Path::new("/bin/sleep")), Some(false));
+}
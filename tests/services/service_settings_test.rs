@@ -0,0 +1,105 @@
+use super::fake_home;
+use flint::global_config::GlobalConfig;
+use flint::launchd_config::{KeepAlive, LaunchdConfig, LaunchdConfigBuilder};
+use flint::services::service_settings::{Origin, Overrides, ServiceSettings};
+use std::collections::BTreeMap;
+
+fn launchd() -> LaunchdConfig {
+    LaunchdConfigBuilder::default()
+        .program(Some("/usr/bin/postgres".to_string()))
+        .args(vec!["-D".to_string(), "/var/pg".to_string()])
+        .environment_variables(BTreeMap::from([
+            ("PGDATA".to_string(), "/var/pg".to_string()),
+            ("LC_ALL".to_string(), "C".to_string()),
+        ]))
+        .working_directory(".".to_string())
+        .stdout_path(Some("/tmp/pg.log".to_string()))
+        .stderr_path(Some("/tmp/pg_error.log".to_string()))
+        .exit_timeout(Some(5))
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn test_layers_merge_field_by_field() {
+    let (_guard, _home) = fake_home();
+    let service = Overrides {
+        environment_variables: BTreeMap::from([("PGDATA".to_string(), "/srv/pg".to_string())]),
+        standard_error_path: Some("/srv/pg_error.log".to_string()),
+        stop_signal: Some("INT".to_string()),
+        exit_timeout: Some(30),
+        ..Overrides::default()
+    };
+    let cli = Overrides {
+        exit_timeout: Some(60),
+        keep_alive: Some(KeepAlive::Always(true)),
+        ..Overrides::default()
+    };
+
+    let settings = ServiceSettings::resolve(
+        "postgres",
+        &launchd(),
+        &GlobalConfig::default(),
+        &[(Origin::Service, &service), (Origin::Cli, &cli)],
+    )
+    .unwrap();
+
+    assert_eq!(settings.args.value, ["-D", "/var/pg"]);
+    assert_eq!(settings.args.origin, Origin::Plist);
+    assert_eq!(settings.stdout_path.value, "/tmp/pg.log");
+    assert_eq!(settings.stdout_path.origin, Origin::Plist);
+    assert_eq!(settings.stderr_path.value, "/srv/pg_error.log");
+    assert_eq!(settings.stderr_path.origin, Origin::Service);
+    assert_eq!(settings.environment_variables["PGDATA"].value, "/srv/pg");
+    assert_eq!(
+        settings.environment_variables["LC_ALL"].origin,
+        Origin::Plist
+    );
+    assert_eq!(settings.stop_signal.value, "INT");
+    assert_eq!(settings.exit_timeout.value, 60);
+    assert_eq!(settings.exit_timeout.origin, Origin::Cli);
+    assert_eq!(settings.keep_alive.value, KeepAlive::Always(true));
+    assert_eq!(settings.working_directory.origin, Origin::Default);
+    assert_eq!(settings.throttle_interval.origin, Origin::Default);
+}
+
+#[test]
+fn test_default_log_paths() {
+    let (_guard, home) = fake_home();
+    let launchd = LaunchdConfigBuilder::default()
+        .program(Some("/bin/true".to_string()))
+        .args(Vec::new())
+        .working_directory("/".to_string())
+        .stdout_path(None)
+        .stderr_path(None)
+        .build()
+        .unwrap();
+
+    let settings =
+        ServiceSettings::resolve("app", &launchd, &GlobalConfig::default(), &[]).unwrap();
+    assert_eq!(
+        settings.stdout_path.value,
+        format!("{}/Library/Logs/Flint/app.log", home.path().display())
+    );
+    assert_eq!(settings.stderr_path.origin, Origin::Default);
+    assert_eq!(settings.working_directory.origin, Origin::Plist);
+}
+
+#[test]
+fn test_invalid_stop_signal_is_rejected() {
+    let (_guard, _home) = fake_home();
+    let service = Overrides {
+        stop_signal: Some("NOPE".to_string()),
+        ..Overrides::default()
+    };
+
+    assert!(
+        ServiceSettings::resolve(
+            "postgres",
+            &launchd(),
+            &GlobalConfig::default(),
+            &[(Origin::Service, &service)]
+        )
+        .is_err()
+    );
+}
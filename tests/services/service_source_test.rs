@@ -0,0 +1,143 @@
+use flint::error::Error;
+use flint::services::service_source::{
+    HomebrewSource, LaunchDirSource, ServiceSource, ServiceSources,
+};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn write_plist(dir: &Path, file_name: &str) {
+    fs::create_dir_all(dir).unwrap();
+    fs::write(dir.join(file_name), b"<plist/>").unwrap();
+}
+
+fn fake_sources() -> (TempDir, ServiceSources) {
+    let tmp = TempDir::new().unwrap();
+    let prefix = tmp.path().join("brew");
+    write_plist(
+        &prefix.join("opt").join("redis"),
+        "homebrew.mxcl.redis.plist",
+    );
+    write_plist(&tmp.path().join("agents"), "com.example.redis.plist");
+    write_plist(&tmp.path().join("agents"), "com.example.worker.plist");
+
+    let mut sources = ServiceSources::default();
+    sources.register(HomebrewSource::new(prefix));
+    sources.register(LaunchDirSource::new("Agents", tmp.path().join("agents")));
+    (tmp, sources)
+}
+
+#[test]
+fn test_earlier_source_takes_precedence() {
+    let (_tmp, sources) = fake_sources();
+
+    let redis = sources.resolve("redis", None).unwrap();
+    assert_eq!(redis.source(), "Homebrew");
+    assert_eq!(redis.label(), "homebrew.mxcl.redis");
+
+    let worker = sources.resolve("example.worker", None).unwrap();
+    assert_eq!(worker.source(), "Agents");
+    assert_eq!(worker.label(), "com.example.worker");
+}
+
+#[test]
+fn test_forced_source() {
+    let (_tmp, sources) = fake_sources();
+
+    let redis = sources.resolve("example.redis", Some("agents")).unwrap();
+    assert_eq!(redis.source(), "Agents");
+    assert!(
+        redis
+            .formula_plist_path()
+            .ends_with("com.example.redis.plist")
+    );
+
+    assert!(matches!(
+        sources.resolve("example.worker", Some("homebrew")),
+        Err(Error::FormulaNotFound { .. })
+    ));
+    assert!(matches!(
+        sources.resolve("redis", Some("nope")),
+        Err(Error::UnknownSource { .. })
+    ));
+}
+
+#[test]
+fn test_discover_lists_each_source_in_order() {
+    let (_tmp, sources) = fake_sources();
+
+    let services = sources.discover(None).unwrap();
+    let sources_seen: Vec<_> = services.iter().map(|s| s.source()).collect();
+    assert_eq!(sources_seen.first(), Some(&"Homebrew"));
+    assert_eq!(services.len(), 3);
+
+    let only_agents = sources.discover(Some("Agents")).unwrap();
+    assert_eq!(only_agents.len(), 2);
+}
+
+#[test]
+fn test_missing_directory_is_empty() {
+    let source = LaunchDirSource::new("Missing", "/does/not/exist".into());
+    assert!(source.discover().unwrap().is_empty());
+    assert!(source.resolve("anything").unwrap().is_none());
+}
+
+#[test]
+fn test_daemon_directory_requires_root() {
+    let tmp = TempDir::new().unwrap();
+    write_plist(tmp.path(), "com.example.daemon.plist");
+
+    let mut sources = ServiceSources::default();
+    sources
+        .register(LaunchDirSource::new("GlobalDaemon", tmp.path().to_path_buf()).requiring_root());
+    sources.register(LaunchDirSource::new("Agents", tmp.path().to_path_buf()));
+
+    assert!(
+        sources
+            .resolve("example.daemon", None)
+            .unwrap()
+            .requires_root()
+    );
+    assert!(
+        !sources
+            .resolve("example.daemon", Some("Agents"))
+            .unwrap()
+            .requires_root()
+    );
+}
+
+#[test]
+fn test_resolve_by_label() {
+    let (_tmp, sources) = fake_sources();
+
+    let redis = sources.resolve_label("homebrew.mxcl.redis", None).unwrap();
+    assert_eq!(redis.source(), "Homebrew");
+    assert_eq!(redis.formula(), "redis");
+
+    assert!(matches!(
+        sources.resolve_label("com.example.redis", None),
+        Err(Error::FormulaNotFound { .. })
+    ));
+}
+
+#[test]
+fn test_plist_label_is_the_identity() {
+    let tmp = TempDir::new().unwrap();
+    let plist = "<?xml version=\"1.0\"?><plist version=\"1.0\"><dict>\
+                 <key>Label</key><string>org.acme.cache</string></dict></plist>";
+    fs::write(tmp.path().join("com.example.redis.plist"), plist).unwrap();
+
+    let mut sources = ServiceSources::default();
+    sources.register(LaunchDirSource::new("Agents", tmp.path().to_path_buf()));
+    sources.register(LaunchDirSource::new("Again", tmp.path().to_path_buf()));
+
+    let by_alias = sources.resolve("example.redis", None).unwrap();
+    assert_eq!(by_alias.label(), "org.acme.cache");
+
+    let by_label = sources.resolve("org.acme.cache", None).unwrap();
+    assert_eq!(by_label.formula(), "example.redis");
+
+    let services = sources.discover(None).unwrap();
+    assert_eq!(services.len(), 1);
+    assert_eq!(services[0].source(), "Agents");
+}
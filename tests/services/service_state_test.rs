@@ -1,18 +1,41 @@
 #[cfg(test)]
 use flint::error::Result;
 use flint::launchd_config::{LaunchdConfig, LaunchdConfigBuilder};
-use flint::services::service_state::{ServiceState, ServiceStateData, ServiceStatus};
+use flint::services::service_state::{
+    HISTORY_LIMIT, STATE_DIR_ENV, STATE_SCHEMA_VERSION, ServiceState, ServiceStateData,
+    ServiceStatus, StartedBy, is_label_listed, migrate_state_files,
+};
 use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::sync::OnceLock;
 use tempfile::TempDir;
 
+/// Points `FLINT_STATE_DIR` at a temporary directory shared by the tests of this module.
+fn state_dir() -> &'static PathBuf {
+    static STATE_DIR: OnceLock<PathBuf> = OnceLock::new();
+    STATE_DIR.get_or_init(|| {
+        let dir = TempDir::new().unwrap().keep();
+        unsafe {
+            std::env::set_var(STATE_DIR_ENV, &dir);
+        }
+        dir
+    })
+}
+
+fn new_state(label: &str) -> ServiceState {
+    state_dir();
+    ServiceState::new(label, &foo_launchd_config().unwrap())
+}
+
 fn foo_launchd_config() -> Result<LaunchdConfig> {
-    Ok(LaunchdConfigBuilder::default()
+    LaunchdConfigBuilder::default()
         .program(Some("/usr/bin/foo".to_string()))
         .args(vec!["--option".to_string(), "value".to_string()])
         .stdout_path(Some("/var/log/foo_stdout.log".to_string()))
         .stderr_path(Some("/var/log/foo_stderr.log".to_string()))
         .working_directory("/usr/local/foo".to_string())
-        .build()?)
+        .build()
 }
 
 #[test]
@@ -20,7 +43,6 @@ fn test_write_and_read_state() {
     let tmp = TempDir::new().unwrap();
     let formula = "demo_service";
 
-    // Temporarily change /tmp/flint to our temp directory
     let dir = tmp.path().join("flint");
     fs::create_dir_all(&dir).unwrap();
     let path = dir.join(format!("{}.state.json", formula));
@@ -38,7 +60,7 @@ fn test_write_and_read_state() {
 #[test]
 fn test_mark_stopped() {
     let formula = "stoppable_service";
-    let state = ServiceState::new(formula.to_string(), &foo_launchd_config().unwrap());
+    let state = new_state(formula);
 
     // Write initial running state
     state.mark_running(vec![5678]).unwrap();
@@ -53,7 +75,7 @@ fn test_mark_stopped() {
 #[test]
 fn test_mark_running_writes_correct_state() {
     let formula = "mark_running_service";
-    let state = ServiceState::new(formula.to_string(), &foo_launchd_config().unwrap());
+    let state = new_state(formula);
 
     state.mark_running(vec![4242]).unwrap();
     let read = state.read_state().unwrap();
@@ -65,7 +87,7 @@ fn test_mark_running_writes_correct_state() {
 #[test]
 fn test_read_state_returns_stale_when_missing() {
     let formula = "missing_service";
-    let state = ServiceState::new(formula.to_string(), &foo_launchd_config().unwrap());
+    let state = new_state(formula);
 
     // No file written
     let data = state.read_state().unwrap();
@@ -77,7 +99,7 @@ fn test_read_state_returns_stale_when_missing() {
 #[test]
 fn test_get_pid_returns_correct_value() {
     let formula = "pid_service";
-    let state = ServiceState::new(formula.to_string(), &foo_launchd_config().unwrap());
+    let state = new_state(formula);
 
     state.mark_running(vec![7777]).unwrap();
     let data = state.read_state().unwrap();
@@ -88,7 +110,7 @@ fn test_get_pid_returns_correct_value() {
 #[test]
 fn test_state_can_toggle_between_running_and_stopped() {
     let formula = "toggle_service";
-    let state = ServiceState::new(formula.to_string(), &foo_launchd_config().unwrap());
+    let state = new_state(formula);
 
     state.mark_running(vec![1000]).unwrap();
     let running = state.read_state().unwrap();
@@ -107,16 +129,293 @@ fn test_state_can_toggle_between_running_and_stopped() {
 #[test]
 fn test_formula_returns_correct_value() {
     let formula = "test_formula_service";
-    let state = ServiceState::new(formula.to_string(), &foo_launchd_config().unwrap());
-    assert_eq!(state.formula(), formula);
+    let state = new_state(formula);
+    assert_eq!(state.label(), formula);
 }
 
 #[test]
 fn test_search_pids_returns_empty_on_no_process() {
     let formula = "dummy_service";
-    let state = ServiceState::new(formula.to_string(), &foo_launchd_config().unwrap());
+    let state = new_state(formula);
 
     // Most likely there is no process with a random binary name
     let pids = state.search_pids().unwrap();
     assert!(pids.is_empty());
 }
+
+#[test]
+fn test_mark_restarted_counts_restarts() {
+    let formula = "restarted_service";
+    let state = new_state(formula);
+
+    let pid = std::process::id() as i32;
+
+    state.mark_spawned(pid, StartedBy::Start).unwrap();
+    state.mark_restarted(pid).unwrap();
+    state.mark_restarted(pid).unwrap();
+    let data = state.read_state().unwrap();
+
+    assert_eq!(data.pids(), [pid]);
+    assert_eq!(data.restarts(), 2);
+
+    state.mark_spawned(pid, StartedBy::Start).unwrap();
+    assert_eq!(state.read_state().unwrap().restarts(), 0);
+}
+
+#[test]
+fn test_mark_spawned_records_pid_and_group() {
+    let formula = "spawned_service";
+    let state = new_state(formula);
+    let pid = std::process::id() as i32;
+
+    state.mark_spawned(pid, StartedBy::Start).unwrap();
+    let data = state.read_state().unwrap();
+
+    assert_eq!(*data.status(), ServiceStatus::Running);
+    assert_eq!(data.pids(), [pid]);
+    assert_eq!(data.pgid(), Some(pid));
+}
+
+#[test]
+fn test_read_state_detects_recycled_pid() {
+    let formula = "recycled_service";
+    let state = new_state(formula);
+    let pid = std::process::id();
+
+    // Our own PID, but with a start time that does not match this process.
+    fs::write(
+        state.state_file_path().unwrap(),
+        format!(
+            r#"{{"pids":[{pid}],"status":"Running","pgid":{pid},"process_start":"not-this-process"}}"#
+        ),
+    )
+    .unwrap();
+
+    let data = state.read_state().unwrap();
+    assert_eq!(*data.status(), ServiceStatus::Stale);
+    assert!(data.pids().is_empty());
+}
+
+#[test]
+fn test_is_label_listed_matches_exact_label() {
+    let list = "PID\tStatus\tLabel\n\
+                123\t0\tcom.example.myredis\n\
+                -\t0\thomebrew.mxcl.postgresql@16\n";
+
+    assert!(is_label_listed(list, "com.example.myredis"));
+    assert!(is_label_listed(list, "homebrew.mxcl.postgresql@16"));
+    assert!(!is_label_listed(list, "redis"));
+    assert!(!is_label_listed(list, "homebrew.mxcl.postgresql"));
+}
+
+#[test]
+fn test_state_moves_from_alias_to_label() {
+    let config = foo_launchd_config().unwrap();
+    let legacy = new_state("alias_service");
+    legacy.mark_running(vec![4242]).unwrap();
+
+    let state = ServiceState::new("com.example.alias_service", &config).with_alias("alias_service");
+    assert_eq!(state.read_state().unwrap().pids(), [4242]);
+    assert_eq!(legacy.read_state().unwrap().status(), &ServiceStatus::Stale);
+    state.mark_stopped().unwrap();
+}
+
+#[test]
+fn test_corrupt_state_is_recovered() {
+    let state = new_state("corrupt_service");
+    state.mark_running(vec![4242]).unwrap();
+    let path = state.state_file_path().unwrap();
+    fs::write(&path, "{\"pids\": [42").unwrap();
+
+    let data = state.read_state().unwrap();
+    assert_eq!(data.status(), &ServiceStatus::Stopped);
+    assert!(data.pids().is_empty());
+    assert!(path.with_extension("json.corrupt").exists());
+
+    state.mark_stopped().unwrap();
+    assert_eq!(
+        state.read_state().unwrap().status(),
+        &ServiceStatus::Stopped
+    );
+}
+
+#[test]
+fn test_concurrent_updates_are_not_lost() {
+    state_dir();
+    let config = foo_launchd_config().unwrap();
+    let pid = std::process::id() as i32;
+    ServiceState::new("concurrent_service", &config)
+        .mark_spawned(pid, StartedBy::Start)
+        .unwrap();
+
+    let threads: Vec<_> = (0..8)
+        .map(|_| {
+            let config = config.clone();
+            std::thread::spawn(move || {
+                let state = ServiceState::new("concurrent_service", &config);
+                for _ in 0..5 {
+                    state.mark_restarted(pid).unwrap();
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let state = ServiceState::new("concurrent_service", &config);
+    assert_eq!(state.read_state().unwrap().restarts(), 40);
+}
+
+#[test]
+fn test_state_dir_is_private() {
+    let state = new_state("private_service");
+    let path = state.state_file_path().unwrap();
+    assert!(path.starts_with(state_dir()));
+
+    let mode = fs::metadata(state_dir()).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o700);
+}
+
+#[test]
+fn test_migrate_state_files() {
+    let legacy = TempDir::new().unwrap();
+    let target = TempDir::new().unwrap();
+    fs::write(legacy.path().join("redis.state.json"), "{}").unwrap();
+    fs::write(legacy.path().join("notes.txt"), "").unwrap();
+    fs::write(legacy.path().join("kept.state.json"), "old").unwrap();
+    fs::write(target.path().join("kept.state.json"), "new").unwrap();
+
+    assert_eq!(
+        migrate_state_files(legacy.path(), target.path()).unwrap(),
+        1
+    );
+    assert!(target.path().join("redis.state.json").exists());
+    assert!(!legacy.path().join("redis.state.json").exists());
+    assert!(!target.path().join("notes.txt").exists());
+    assert_eq!(
+        fs::read_to_string(target.path().join("kept.state.json")).unwrap(),
+        "new"
+    );
+}
+
+#[test]
+fn test_exit_and_history_are_recorded() {
+    let state = new_state("history_service");
+    let pid = std::process::id() as i32;
+    state.mark_spawned(pid, StartedBy::Supervise).unwrap();
+    state.mark_exited(None, Some(9)).unwrap();
+
+    let data = state.read_state().unwrap();
+    assert_eq!(data.schema_version(), STATE_SCHEMA_VERSION);
+    assert_eq!(data.started_by(), Some(StartedBy::Supervise));
+    assert_eq!(data.last_exit_signal(), Some(9));
+    assert!(data.since_last_exit().is_some());
+    assert_eq!(data.history().len(), 2);
+
+    for _ in 0..HISTORY_LIMIT {
+        state.mark_restarted(pid).unwrap();
+    }
+    let data = state.read_state().unwrap();
+    assert_eq!(data.history().len(), HISTORY_LIMIT);
+    assert_eq!(data.last_exit_signal(), Some(9));
+}
+
+#[test]
+fn test_unversioned_state_is_migrated() {
+    let state = new_state("unversioned_service");
+    fs::write(
+        state.state_file_path().unwrap(),
+        r#"{"pids":[],"status":"Stopped"}"#,
+    )
+    .unwrap();
+
+    let data = state.read_state().unwrap();
+    assert_eq!(data.schema_version(), STATE_SCHEMA_VERSION);
+    assert!(data.history().is_empty());
+}
+
+fn sleep_state(label: &str) -> ServiceState {
+    state_dir();
+    let config = LaunchdConfigBuilder::default()
+        .program(Some("/bin/sleep".to_string()))
+        .args(vec!["30".to_string()])
+        .stdout_path(None)
+        .stderr_path(None)
+        .working_directory("/tmp".to_string())
+        .build()
+        .unwrap();
+    ServiceState::new(label, &config)
+}
+
+#[test]
+fn test_reconcile_marks_dead_service_crashed() {
+    let state = sleep_state("vanished_service");
+    let mut child = std::process::Command::new("/bin/sleep")
+        .arg("30")
+        .spawn()
+        .unwrap();
+    state
+        .mark_spawned(child.id() as i32, StartedBy::Start)
+        .unwrap();
+    assert_eq!(
+        state.reconcile(false).unwrap().status(),
+        &ServiceStatus::Running
+    );
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+    let data = state.reconcile(false).unwrap();
+    assert_eq!(data.status(), &ServiceStatus::Crashed);
+    assert!(data.pids().is_empty());
+    assert!(data.since_last_exit().is_some());
+    assert_eq!(
+        state.read_state().unwrap().status(),
+        &ServiceStatus::Crashed
+    );
+}
+
+#[test]
+fn test_reconcile_drops_pids_of_other_programs() {
+    let state = sleep_state("foreign_service");
+    // This test binary is alive but is not /bin/sleep.
+    state.mark_running(vec![std::process::id() as i32]).unwrap();
+
+    let data = state.reconcile(false).unwrap();
+    assert_eq!(data.status(), &ServiceStatus::Crashed);
+}
+
+#[test]
+fn test_reconcile_adopts_external_process() {
+    let state = sleep_state("external_service");
+    let mut child = std::process::Command::new("/bin/sleep")
+        .arg("30")
+        .spawn()
+        .unwrap();
+    let pid = child.id() as i32;
+
+    assert_ne!(
+        state.reconcile(false).unwrap().status(),
+        &ServiceStatus::Running
+    );
+    let data = state.reconcile(true).unwrap();
+    assert_eq!(data.status(), &ServiceStatus::Running);
+    assert!(data.pids().contains(&pid));
+    assert_eq!(data.started_by(), Some(StartedBy::Adopt));
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[test]
+fn test_mark_exited_distinguishes_success_from_failure() {
+    let state = new_state("exit_status_service");
+    state.mark_exited(Some(0), None).unwrap();
+    assert_eq!(state.read_state().unwrap().status(), &ServiceStatus::Exited);
+
+    state.mark_exited(Some(1), None).unwrap();
+    assert_eq!(
+        state.read_state().unwrap().status(),
+        &ServiceStatus::Crashed
+    );
+}
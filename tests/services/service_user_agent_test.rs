@@ -0,0 +1,77 @@
+use flint::error::Error;
+use flint::services::service_user_agent::ServiceUserAgent;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn write_plist(dir: &Path, file_name: &str, label: &str) {
+    let plist = format!(
+        "<?xml version=\"1.0\"?><plist version=\"1.0\"><dict>\
+         <key>Label</key><string>{}</string></dict></plist>",
+        label
+    );
+    fs::write(dir.join(file_name), plist).unwrap();
+}
+
+#[test]
+fn test_short_name_does_not_match_suffix() {
+    let tmp = TempDir::new().unwrap();
+    write_plist(tmp.path(), "com.foo.myredis.plist", "com.foo.myredis");
+
+    assert!(
+        ServiceUserAgent::find_plist_in(tmp.path(), "redis")
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        ServiceUserAgent::find_plist_in(tmp.path(), "foo.myredis")
+            .unwrap()
+            .is_some()
+    );
+}
+
+#[test]
+fn test_ambiguous_short_name() {
+    let tmp = TempDir::new().unwrap();
+    write_plist(
+        tmp.path(),
+        "com.acme.app.worker.plist",
+        "com.acme.app.worker",
+    );
+    write_plist(
+        tmp.path(),
+        "org.other.app.worker.plist",
+        "org.other.app.worker",
+    );
+
+    match ServiceUserAgent::find_plist_in(tmp.path(), "app.worker") {
+        Err(Error::AmbiguousFormula { candidates, .. }) => {
+            assert_eq!(candidates.len(), 2);
+            assert!(candidates[0].ends_with("com.acme.app.worker.plist"));
+        }
+        other => panic!("expected an ambiguity error, got {:?}", other),
+    }
+
+    // Colliding short names are listed by their full stem, which resolves exactly.
+    let formulas = ServiceUserAgent::formulas_in(tmp.path()).unwrap();
+    assert_eq!(formulas, ["com.acme.app.worker", "org.other.app.worker"]);
+    assert!(
+        ServiceUserAgent::find_plist_in(tmp.path(), "org.other.app.worker")
+            .unwrap()
+            .is_some()
+    );
+}
+
+#[test]
+fn test_exact_label_wins_over_short_name() {
+    let tmp = TempDir::new().unwrap();
+    write_plist(tmp.path(), "com.example.redis.plist", "com.example.redis");
+    write_plist(tmp.path(), "custom.plist", "redis");
+
+    let found = ServiceUserAgent::find_plist_in(tmp.path(), "redis").unwrap();
+    assert_eq!(found, Some(tmp.path().join("custom.plist")));
+
+    let by_label =
+        ServiceUserAgent::find_plist_by_label_in(tmp.path(), "com.example.redis").unwrap();
+    assert_eq!(by_label, Some(tmp.path().join("com.example.redis.plist")));
+}
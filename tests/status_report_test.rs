@@ -0,0 +1,178 @@
+use flint::global_config::GlobalConfig;
+use flint::launchd_config::LaunchdConfigBuilder;
+use flint::services::service_settings::{Origin, Overrides, ServiceSettings};
+use flint::services::service_state::{HistoryEntry, LifecycleEvent, ServiceStatus, StartedBy};
+use flint::status_report::{
+    OutputFormat, StatusReport, format_duration, render_all, render_info, render_one,
+    render_settings,
+};
+use std::time::Duration;
+
+fn report(formula: &str, source: &str, status: ServiceStatus) -> StatusReport {
+    StatusReport {
+        formula: formula.to_string(),
+        label: format!("homebrew.mxcl.{}", formula),
+        source: source.to_string(),
+        plist_path: format!("/tmp/{}.plist", formula),
+        status,
+        pids: vec![42],
+        uptime_secs: Some(125),
+        stdout_path: format!("/tmp/{}.log", formula),
+        stderr_path: format!("/tmp/{}_error.log", formula),
+        last_exit_code: None,
+        requires_root: false,
+        started_at: None,
+        started_by: None,
+        restarts: 0,
+        last_exit_signal: None,
+        last_exit_secs_ago: None,
+        health: None,
+        history: Vec::new(),
+    }
+}
+
+#[test]
+fn test_json_round_trips_with_stable_keys() {
+    let reports = vec![report("redis", "Homebrew", ServiceStatus::Running)];
+    let json = render_all(&reports, OutputFormat::Json).unwrap();
+
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value[0]["formula"], "redis");
+    assert_eq!(value[0]["source"], "Homebrew");
+    assert_eq!(value[0]["status"], "Running");
+    assert_eq!(value[0]["uptime_secs"], 125);
+    assert!(value[0]["last_exit_code"].is_null());
+
+    let parsed: Vec<StatusReport> = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, reports);
+}
+
+#[test]
+fn test_yaml_single_report() {
+    let yaml = render_one(
+        &report("redis", "Homebrew", ServiceStatus::Stopped),
+        OutputFormat::Yaml,
+    )
+    .unwrap();
+    assert!(yaml.contains("formula: redis"));
+    assert!(yaml.contains("status: Stopped"));
+}
+
+#[test]
+fn test_table_groups_by_source() {
+    let reports = vec![
+        report("redis", "Homebrew", ServiceStatus::Running),
+        report("postgres", "Homebrew", ServiceStatus::Stopped),
+        report("example.agent", "UserAgent", ServiceStatus::Stale),
+    ];
+    let table = render_all(&reports, OutputFormat::Table).unwrap();
+
+    assert_eq!(table.matches("Homebrew").count(), 1);
+    assert_eq!(table.matches("UserAgent").count(), 1);
+    assert!(table.contains("redis"));
+    assert!(table.contains("2m 5s"));
+}
+
+#[test]
+fn test_format_duration() {
+    assert_eq!(format_duration(Duration::from_secs(9)), "9s");
+    assert_eq!(format_duration(Duration::from_secs(125)), "2m 5s");
+    assert_eq!(
+        format_duration(Duration::from_secs(3 * 3600 + 720)),
+        "3h 12m"
+    );
+    assert_eq!(
+        format_duration(Duration::from_secs(2 * 86_400 + 3600)),
+        "2d 1h"
+    );
+}
+
+#[test]
+fn test_table_shows_last_exit() {
+    let mut stopped = report("redis", "Homebrew", ServiceStatus::Stopped);
+    stopped.uptime_secs = None;
+    stopped.last_exit_signal = Some(9);
+    stopped.last_exit_secs_ago = Some(180);
+
+    let line = render_one(&stopped, OutputFormat::Table).unwrap();
+    assert_eq!(
+        line,
+        "Service 'redis' is not running. Last exited 3m 0s ago with signal 9."
+    );
+}
+
+#[test]
+fn test_info_lists_history() {
+    let mut running = report("redis", "Homebrew", ServiceStatus::Running);
+    running.started_by = Some(StartedBy::Supervise);
+    running.restarts = 1;
+    running.history = vec![
+        HistoryEntry {
+            at: 0,
+            event: LifecycleEvent::Started {
+                pid: 41,
+                by: StartedBy::Supervise,
+            },
+        },
+        HistoryEntry {
+            at: 0,
+            event: LifecycleEvent::Exited {
+                code: Some(1),
+                signal: None,
+            },
+        },
+    ];
+
+    let info = render_info(&running, OutputFormat::Table).unwrap();
+    assert!(info.contains("Started by:   supervise"));
+    assert!(info.contains("Restarts:     1"));
+    assert!(info.contains("started as PID 41 by supervise"));
+    assert!(info.contains("exited with code 1"));
+
+    let json: serde_json::Value =
+        serde_json::from_str(&render_info(&running, OutputFormat::Json).unwrap()).unwrap();
+    assert_eq!(json["history"][0]["event"], "started");
+    assert_eq!(json["history"][0]["by"], "supervise");
+}
+
+#[test]
+fn test_settings_table_shows_origins() {
+    let launchd = LaunchdConfigBuilder::default()
+        .program(Some("/usr/bin/redis-server".to_string()))
+        .args(vec!["/etc/redis.conf".to_string()])
+        .working_directory("/".to_string())
+        .stdout_path(Some("/tmp/redis.log".to_string()))
+        .stderr_path(None)
+        .build()
+        .unwrap();
+    let cli = Overrides {
+        environment_variables: [("DEBUG".to_string(), "1".to_string())].into(),
+        ..Overrides::default()
+    };
+    let settings = ServiceSettings::resolve(
+        "redis",
+        &launchd,
+        &GlobalConfig::default(),
+        &[(Origin::Cli, &cli)],
+    )
+    .unwrap();
+
+    let table = render_settings(&settings, OutputFormat::Table).unwrap();
+    let line = |name: &str| {
+        table
+            .lines()
+            .find(|line| line.starts_with(name))
+            .unwrap()
+            .to_string()
+    };
+    assert!(line("args ").contains("/etc/redis.conf"));
+    assert!(line("args ").ends_with("(plist)"));
+    assert!(line("stop_signal ").contains("SIGTERM"));
+    assert!(line("stop_signal ").ends_with("(default)"));
+    assert!(line("env.DEBUG ").ends_with("(command line)"));
+
+    let json: serde_json::Value =
+        serde_json::from_str(&render_settings(&settings, OutputFormat::Json).unwrap()).unwrap();
+    assert_eq!(json["stdout_path"]["origin"], "plist");
+    assert_eq!(json["exit_timeout"]["value"], 20);
+}
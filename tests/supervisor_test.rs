@@ -0,0 +1,92 @@
+use flint::launchd_config::{KeepAlive, KeepAliveConditions};
+use flint::supervisor::should_restart;
+use std::collections::BTreeMap;
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+
+fn exited(code: i32) -> ExitStatus {
+    ExitStatus::from_raw(code << 8)
+}
+
+fn signaled(signal: i32) -> ExitStatus {
+    ExitStatus::from_raw(signal)
+}
+
+fn never_running(_: &str) -> bool {
+    false
+}
+
+#[test]
+fn test_no_keep_alive_never_restarts() {
+    assert!(!should_restart(None, &exited(1), never_running));
+    assert!(!should_restart(
+        Some(&KeepAlive::Always(false)),
+        &signaled(9),
+        never_running
+    ));
+}
+
+#[test]
+fn test_keep_alive_true_always_restarts() {
+    let keep_alive = KeepAlive::Always(true);
+    assert!(should_restart(Some(&keep_alive), &exited(0), never_running));
+    assert!(should_restart(
+        Some(&keep_alive),
+        &signaled(9),
+        never_running
+    ));
+}
+
+#[test]
+fn test_successful_exit_false_restarts_until_success() {
+    let keep_alive = KeepAlive::Conditions(KeepAliveConditions {
+        successful_exit: Some(false),
+        ..Default::default()
+    });
+    assert!(should_restart(Some(&keep_alive), &exited(1), never_running));
+    assert!(!should_restart(
+        Some(&keep_alive),
+        &exited(0),
+        never_running
+    ));
+}
+
+#[test]
+fn test_crashed_restarts_only_on_signal() {
+    let keep_alive = KeepAlive::Conditions(KeepAliveConditions {
+        crashed: Some(true),
+        ..Default::default()
+    });
+    assert!(should_restart(
+        Some(&keep_alive),
+        &signaled(11),
+        never_running
+    ));
+    assert!(!should_restart(
+        Some(&keep_alive),
+        &exited(1),
+        never_running
+    ));
+}
+
+#[test]
+fn test_path_state_and_other_job_enabled() {
+    let tmp = tempfile::tempdir().unwrap();
+    let keep_alive = KeepAlive::Conditions(KeepAliveConditions {
+        path_state: BTreeMap::from([(tmp.path().display().to_string(), true)]),
+        ..Default::default()
+    });
+    assert!(should_restart(Some(&keep_alive), &exited(0), never_running));
+
+    let keep_alive = KeepAlive::Conditions(KeepAliveConditions {
+        other_job_enabled: BTreeMap::from([("homebrew.mxcl.redis".to_string(), true)]),
+        ..Default::default()
+    });
+    assert!(should_restart(Some(&keep_alive), &exited(0), |label| label
+        == "homebrew.mxcl.redis"));
+    assert!(!should_restart(
+        Some(&keep_alive),
+        &exited(0),
+        never_running
+    ));
+}